@@ -0,0 +1,11 @@
+-- 用户表
+CREATE TABLE IF NOT EXISTS users (
+    id         BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    email      VARCHAR(255)    NOT NULL,
+    nickname   VARCHAR(30)     NULL,
+    password   VARCHAR(255)    NOT NULL COMMENT 'argon2 哈希',
+    status     TINYINT         NOT NULL DEFAULT 1 COMMENT '1 正常, 0 禁用',
+    created_at DATETIME        NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE KEY uk_email (email)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
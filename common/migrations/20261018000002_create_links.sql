@@ -0,0 +1,15 @@
+-- 短链表
+-- short_code 在插入后由 id 生成再回写，因此允许为 NULL；唯一索引保证短码不重复
+CREATE TABLE IF NOT EXISTS links (
+    id          BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    user_id     BIGINT UNSIGNED NOT NULL,
+    short_code  VARCHAR(64)     NULL,
+    long_url    VARCHAR(2048)   NOT NULL,
+    click_count BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    expire_at   DATETIME        NULL COMMENT 'UTC，NULL 表示永久',
+    created_at  DATETIME        NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE KEY uk_short_code (short_code),
+    KEY idx_user_id_created_at (user_id, created_at),
+    KEY idx_expire_at (expire_at)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
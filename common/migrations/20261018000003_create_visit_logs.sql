@@ -0,0 +1,15 @@
+-- 访问日志表
+-- stream_id 为 Redis Stream 消息 id，唯一索引用于同步重放时去重
+CREATE TABLE IF NOT EXISTS visit_logs (
+    id         BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    stream_id  VARCHAR(32)     NOT NULL,
+    short_code VARCHAR(64)     NOT NULL,
+    long_url   VARCHAR(2048)   NOT NULL,
+    ip         VARCHAR(45)     NOT NULL DEFAULT '',
+    user_agent VARCHAR(1024)   NOT NULL DEFAULT '',
+    referer    VARCHAR(2048)   NOT NULL DEFAULT '',
+    visit_time DATETIME        NOT NULL COMMENT 'UTC',
+    PRIMARY KEY (id),
+    UNIQUE KEY uk_stream_id (stream_id),
+    KEY idx_short_code_visit_time (short_code, visit_time)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
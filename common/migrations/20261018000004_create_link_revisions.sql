@@ -0,0 +1,15 @@
+-- 短链目标地址修改记录
+-- revision 为单条短链内递增的版本号；短链删除时级联删除其修改记录
+CREATE TABLE IF NOT EXISTS link_revisions (
+    id           BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    link_id      BIGINT UNSIGNED NOT NULL,
+    revision     INT UNSIGNED    NOT NULL,
+    user_id      BIGINT UNSIGNED NOT NULL COMMENT '操作人',
+    action       VARCHAR(16)     NOT NULL COMMENT 'update / rollback',
+    old_long_url VARCHAR(2048)   NOT NULL,
+    new_long_url VARCHAR(2048)   NOT NULL,
+    created_at   DATETIME        NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    UNIQUE KEY uk_link_id_revision (link_id, revision),
+    CONSTRAINT fk_link_revisions_link FOREIGN KEY (link_id) REFERENCES links (id) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,13 @@
+-- 短码的形近骨架，用于拒绝与已有短码视觉上易混的自定义短码
+-- 骨架规则与 link-service 中 code_policy::skeleton 一致：转小写，0→o、1/i→l、2→z、5→s、8→b，再把 rn→m、vv→w
+ALTER TABLE links
+    ADD COLUMN code_skeleton VARCHAR(64) NULL AFTER short_code,
+    ADD KEY idx_code_skeleton (code_skeleton);
+
+UPDATE links
+SET code_skeleton = REPLACE(REPLACE(
+        REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+            LOWER(short_code),
+            '0', 'o'), '1', 'l'), 'i', 'l'), '2', 'z'), '5', 's'), '8', 'b'),
+        'rn', 'm'), 'vv', 'w')
+WHERE short_code IS NOT NULL;
@@ -0,0 +1,7 @@
+-- 目标地址哈希：归一化后的 long_url 取 SHA-256，用于按用户复用相同目标地址的短链
+-- 存量数据直接对原始 long_url 取哈希，写法不规范的旧链接可能无法被复用
+ALTER TABLE links
+    ADD COLUMN url_hash BINARY(32) NULL AFTER long_url,
+    ADD KEY idx_user_id_url_hash (user_id, url_hash);
+
+UPDATE links SET url_hash = UNHEX(SHA2(long_url, 256));
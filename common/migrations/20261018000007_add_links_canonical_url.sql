@@ -0,0 +1,7 @@
+-- 目标地址规范形式：用于去重、搜索和黑名单匹配，跳转仍使用原始的 long_url
+-- 存量数据直接以原始地址回填，url_hash 随之保持不变
+ALTER TABLE links ADD COLUMN canonical_url VARCHAR(2048) NULL AFTER long_url;
+
+UPDATE links SET canonical_url = long_url;
+
+ALTER TABLE links MODIFY COLUMN canonical_url VARCHAR(2048) NOT NULL;
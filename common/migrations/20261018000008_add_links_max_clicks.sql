@@ -0,0 +1,5 @@
+-- 限次短链：max_clicks 为允许的跳转次数（NULL 不限），剩余次数在 Redis 中原子扣减
+-- 次数用完时写入 exhausted_at，之后的访问直接返回 410，不再依赖 Redis
+ALTER TABLE links
+    ADD COLUMN max_clicks   INT UNSIGNED NULL COMMENT 'NULL 表示不限次数' AFTER click_count,
+    ADD COLUMN exhausted_at DATETIME     NULL COMMENT 'UTC，次数用完的时间' AFTER max_clicks;
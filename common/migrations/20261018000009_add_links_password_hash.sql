@@ -0,0 +1,2 @@
+-- 密码保护短链：password_hash 为 argon2 哈希（PHC 字符串），NULL 表示不需要密码
+ALTER TABLE links ADD COLUMN password_hash VARCHAR(255) NULL AFTER exhausted_at;
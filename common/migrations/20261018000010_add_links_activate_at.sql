@@ -0,0 +1,3 @@
+-- 定时生效：activate_at 之前访问短链返回"尚未生效"，也不写跳转缓存
+ALTER TABLE links
+    ADD COLUMN activate_at DATETIME NULL COMMENT 'UTC，生效时间，NULL 表示创建即生效' AFTER expire_at;
@@ -0,0 +1,5 @@
+-- 闲置过期：inactivity_days 天内没有点击（从未点击则从创建时起算）的短链视为过期
+-- last_click_at 由点击量同步任务从 Redis 写入
+ALTER TABLE links
+    ADD COLUMN inactivity_days SMALLINT UNSIGNED NULL COMMENT 'NULL 表示不按闲置过期' AFTER activate_at,
+    ADD COLUMN last_click_at   DATETIME          NULL COMMENT 'UTC，最近一次点击时间' AFTER inactivity_days;
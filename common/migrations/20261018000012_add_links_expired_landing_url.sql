@@ -0,0 +1,3 @@
+-- 过期宽限期：宽限期内访问已过期短链返回 410，页面引导到 expired_landing_url（由短链所有者设置）
+ALTER TABLE links
+    ADD COLUMN expired_landing_url VARCHAR(2048) NULL COMMENT '过期后的落地页，NULL 表示不跳转' AFTER last_click_at;
@@ -0,0 +1,8 @@
+-- 定时任务围栏令牌：非幂等作业在写入事务内把租约的 fence 写入并复核，
+-- 已有更大的 fence 说明租约已被其他副本接管，旧持有者的事务回滚
+CREATE TABLE IF NOT EXISTS job_fences (
+    job        VARCHAR(64)     NOT NULL,
+    fence      BIGINT UNSIGNED NOT NULL,
+    updated_at DATETIME        NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (job)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
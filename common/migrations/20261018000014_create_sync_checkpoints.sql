@@ -0,0 +1,8 @@
+-- 同步任务检查点：记录最近一次提交的批次号
+-- 点击量同步在更新 links 的同一事务内写入，提交后、清理 Redis 暂存前中断时据此识别已提交的批次，避免重复累加
+CREATE TABLE IF NOT EXISTS sync_checkpoints (
+    name       VARCHAR(64) NOT NULL,
+    batch_id   VARCHAR(64) NOT NULL,
+    updated_at DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (name)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
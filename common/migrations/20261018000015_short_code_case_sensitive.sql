@@ -0,0 +1,9 @@
+-- 短码区分大小写：生成器和自定义短码字母表都是大小写混合的 base62，
+-- 表默认的 utf8mb4_unicode_ci 会让 abc 和 ABC 视为同一个短码（跳转查到别的短链、预检把可用短码当成已占用），
+-- 与区分大小写的 Redis 缓存 key 也不一致；访问日志的 short_code 一并修改，按短码统计时不会合并。
+-- 不用 utf8mb4_bin：二进制排序规则的列带 BINARY 标志，sqlx 不能把它解码为 String
+ALTER TABLE links
+    MODIFY COLUMN short_code VARCHAR(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_0900_as_cs NULL;
+
+ALTER TABLE visit_logs
+    MODIFY COLUMN short_code VARCHAR(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_0900_as_cs NOT NULL;
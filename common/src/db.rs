@@ -53,3 +53,11 @@ pub async fn new_mysql_pool(
         .connect(url)
         .await
 }
+
+/// 执行数据库迁移（`common/migrations`）
+///
+/// 两个服务共用同一个库和同一张 `_sqlx_migrations` 表，迁移脚本统一放在 common 下；
+/// sqlx 在执行前会加锁，多个实例同时启动也只会有一个真正执行。
+pub async fn run_migrations(pool: &MySqlPool) -> Result<(), sqlx::migrate::MigrateError> {
+    sqlx::migrate!("./migrations").run(pool).await
+}
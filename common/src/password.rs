@@ -0,0 +1,60 @@
+//! 密码哈希（argon2）
+//!
+//! user-service 的账号密码和 link-service 的短链访问密码共用这里的实现。argon2 每次哈希、校验都要
+//! 占用数十毫秒 CPU，放到阻塞线程池执行，避免占住 tokio 工作线程拖慢同一线程上的其他请求。
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use password_hash::{PasswordHasher, SaltString, rand_core::OsRng};
+use tracing::warn;
+
+/// 生成随机盐并计算密码哈希
+pub async fn hash_password(password: &str) -> Result<String, String> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| {
+                warn!("hash_password: password encryption failed: err={}", e);
+                format!("Password encryption failed: {}", e)
+            })
+    })
+    .await
+    .map_err(|e| {
+        warn!("hash_password: blocking task failed: err={}", e);
+        format!("Password task failed: {}", e)
+    })?
+}
+
+/// 校验密码，哈希无法解析时返回错误，密码不匹配返回 `Ok(false)`
+pub async fn verify_password(password: &str, password_hash: &str) -> Result<bool, String> {
+    let (password, password_hash) = (password.to_string(), password_hash.to_string());
+    tokio::task::spawn_blocking(move || {
+        let parsed_hash = PasswordHash::new(&password_hash).map_err(|_| {
+            warn!("verify_password: password hash parse failed");
+            "Password hash parse failed".to_string()
+        })?;
+
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| {
+        warn!("verify_password: blocking task failed: err={}", e);
+        format!("Password task failed: {}", e)
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let hash = hash_password("s3cret").await.unwrap();
+        assert!(verify_password("s3cret", &hash).await.unwrap());
+        assert!(!verify_password("wrong", &hash).await.unwrap());
+        assert!(verify_password("s3cret", "not-a-hash").await.is_err());
+    }
+}
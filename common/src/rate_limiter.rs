@@ -31,3 +31,13 @@ pub async fn rate_limit(
 
     Ok(())
 }
+
+/// 只读取计数判断是否已达到上限，不增加计数；与 [`rate_limit`] 配合，只对失败的请求计数
+pub async fn is_limited(key: &str, limit: i64, conn: &mut Connection) -> Result<bool, String> {
+    let count: Option<i64> = conn.get(key).await.map_err(|e| {
+        warn!("rate_limiter: Redis Get 失败, key={}, err={}", key, e);
+        format!("Redis Get err: {}", e)
+    })?;
+
+    Ok(count.unwrap_or(0) >= limit)
+}
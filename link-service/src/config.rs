@@ -3,6 +3,11 @@ use serde::Deserialize;
 // use dotenvy;
 // use std::env;
 
+/// 定时任务租约有效期下限（毫秒），需远大于一次续约的 Redis 往返
+const MIN_JOB_LEASE_TTL_MS: u64 = 1000;
+/// 访问尚未生效的短链时允许返回的状态码
+const LINK_NOT_ACTIVE_STATUSES: [u16; 4] = [403, 404, 425, 503];
+
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     /// MySQL 连接字符串
@@ -19,10 +24,60 @@ pub struct AppConfig {
     pub shortlink_min_ttl: i64,
     /// 短链的最大过期时间
     pub shortlink_max_ttl: i64,
+    /// 是否允许创建永久（不过期）短链
+    pub shortlink_allow_permanent: bool,
+    /// 批量创建短链单次最多条数
+    pub shortlink_batch_max_items: usize,
+    /// 短码生成策略：sequential / random / feistel
+    pub short_code_strategy: String,
+    /// 自动生成短码的长度（sequential 为最小长度，不足左侧补齐）
+    pub short_code_length: usize,
+    /// 短码字母表
+    pub short_code_alphabet: String,
+    /// feistel 策略的置换密钥，更换后新生成的短码序列会改变
+    pub short_code_secret: String,
+    /// 自定义短码允许的字符
+    pub custom_code_alphabet: String,
+    /// 自定义短码最小长度
+    pub custom_code_min_len: usize,
+    /// 自定义短码最大长度（不超过 64）
+    pub custom_code_max_len: usize,
+    /// 短码保留词文件，每行一个，为空不加载
+    pub short_code_reserved_file: String,
+    /// 短码不雅词文件，每行一个，为空不加载
+    pub short_code_profanity_file: String,
+    /// 规范化目标地址时是否按参数名排序查询参数
+    pub url_sort_query_params: bool,
+    /// 规范化目标地址时去掉的查询参数，逗号分隔，`*` 结尾表示前缀匹配（如 `utm_*`），为空不去掉
+    pub url_strip_query_params: String,
+    /// 目标地址允许的协议，逗号分隔
+    pub destination_allowed_schemes: String,
+    /// 目标域名黑名单文件，每行一个，匹配域名及其子域名，为空不加载
+    pub destination_blocklist_file: String,
+    /// 检查黑名单文件是否修改并重新加载的间隔（秒）
+    pub destination_blocklist_reload_interval: u64,
+    /// 本服务对外的短链域名，逗号分隔；指向这些域名（及服务监听地址）的目标地址会被拒绝
+    pub shortlink_self_hosts: String,
+    /// 过期宽限期（秒）：期间已过期的短链返回 410 并保留数据，可由所有者重新激活，之后才被清理；不能为负数
+    pub shortlink_expiry_grace_period: i64,
+    /// 访问尚未到生效时间的短链时返回的 HTTP 状态码，只允许 403、404、425、503
+    pub link_not_active_status: u16,
+    /// 访问尚未到生效时间的短链时返回的提示信息
+    pub link_not_active_message: String,
+    /// 密码保护短链解锁后 Cookie 的有效期（秒）
+    pub link_unlock_ttl: i64,
+    /// 解锁 Cookie 是否带 `Secure` 属性（仅 HTTPS 部署时开启）
+    pub link_unlock_cookie_secure: bool,
+    /// 单 IP + 短码解锁失败次数阈值
+    pub link_unlock_fail_limit: i64,
+    /// 单 IP + 短码解锁失败计数窗口（秒）
+    pub link_unlock_fail_window: i64,
     /// Redis 的最大过期时间
     pub redis_max_ttl: i64,
     /// Redis 的最小缓存时间
     pub redis_min_cache_ttl: i64,
+    /// 编辑短链后延迟二次删除跳转缓存的时间（毫秒）
+    pub cache_double_delete_delay_ms: u64,
     /// 最大统计天数
     pub max_stats_days: u8,
     /// IP 限流
@@ -57,6 +112,8 @@ pub struct AppConfig {
     pub mysql_query_timeout_ms: u64,
     /// InnoDB 表中等待锁的最大时间（秒）
     pub mysql_lock_wait_timeout_s: u64,
+    /// 启动时是否执行数据库迁移
+    pub run_migrations: bool,
     /// Redis 连接池最大连接数
     pub redis_pool_size: usize,
     /// 等待空闲连接的最大时间（毫秒）
@@ -69,12 +126,36 @@ pub struct AppConfig {
     pub bg_redis_queue_cap: usize,
     /// Redis 后台作业最大并发数
     pub bg_redis_max_concurrency: usize,
+    /// 队列满时溢出缓冲区的容量，0 表示不缓冲直接在请求内写入
+    pub bg_spill_cap: usize,
+    /// 溢出缓冲区回灌队列的间隔（毫秒）
+    pub bg_spill_flush_interval_ms: u64,
+    /// 关闭时等待后台作业排空的最长时间（毫秒），最后一次同步另计同样时长
+    pub shutdown_timeout_ms: u64,
     /// 过期短链删除任务的执行间隔（秒）
     pub bg_expired_links_sync_interval: u64,
     /// 点击量同步任务的执行间隔（秒）
     pub bg_click_counts_sync_interval: u64,
     /// 访问日志同步任务的执行间隔（秒）
     pub bg_visit_logs_sync_interval: u64,
+    /// 过期短链清理每块处理的短链数，必须大于 0
+    pub bg_purge_chunk_size: u64,
+    /// 过期短链清理单次执行最多处理的块数
+    pub bg_purge_max_chunks: u64,
+    /// 过期短链清理时单条 DELETE 删除访问日志的最大行数，必须大于 0
+    pub bg_purge_log_batch_size: u64,
+    /// 定时任务每次触发附加的最大随机抖动（毫秒）
+    pub bg_jobs_max_jitter_ms: u64,
+    /// 定时任务跨副本租约的有效期（毫秒），执行期间按 1/3 周期续约，不能小于 1000
+    pub bg_job_lease_ttl_ms: u64,
+    /// 访问日志同步每次从 Stream 读取的条数
+    pub visit_log_sync_batch_size: usize,
+    /// 访问日志批量插入时单条 INSERT 语句的最大行数
+    pub visit_log_insert_max_rows: usize,
+    /// 访问日志待确认条目空闲超过该时长（毫秒）后可被其他消费者接管
+    pub visit_log_claim_idle_ms: u64,
+    /// 访问日志条目最大投递次数，超过后移入死信 Stream
+    pub visit_log_max_deliveries: usize,
 }
 
 impl AppConfig {
@@ -82,10 +163,41 @@ impl AppConfig {
         // 根据 ENV_FILE 环境变量指定的文件加载环境变量，默认使用 ".env"
         // let env_file = env::var("ENV_FILE").unwrap_or_else(|_| ".env".to_string());
         // dotenvy::from_filename(&env_file).ok();
-        Config::builder()
+        let cfg: Self = Config::builder()
             .add_source(Environment::default())
             .build()?
-            .try_deserialize()
+            .try_deserialize()?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// 校验取值范围，避免错误配置在运行中才暴露
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.bg_job_lease_ttl_ms < MIN_JOB_LEASE_TTL_MS {
+            return Err(ConfigError::Message(format!(
+                "bg_job_lease_ttl_ms must be at least {}, got {}",
+                MIN_JOB_LEASE_TTL_MS, self.bg_job_lease_ttl_ms
+            )));
+        }
+        if self.bg_purge_chunk_size == 0 || self.bg_purge_log_batch_size == 0 {
+            return Err(ConfigError::Message(format!(
+                "bg_purge_chunk_size and bg_purge_log_batch_size must be positive, got {} and {}",
+                self.bg_purge_chunk_size, self.bg_purge_log_batch_size
+            )));
+        }
+        if self.shortlink_expiry_grace_period < 0 {
+            return Err(ConfigError::Message(format!(
+                "shortlink_expiry_grace_period must not be negative, got {}",
+                self.shortlink_expiry_grace_period
+            )));
+        }
+        if !LINK_NOT_ACTIVE_STATUSES.contains(&self.link_not_active_status) {
+            return Err(ConfigError::Message(format!(
+                "link_not_active_status must be one of {:?}, got {}",
+                LINK_NOT_ACTIVE_STATUSES, self.link_not_active_status
+            )));
+        }
+        Ok(())
     }
 }
 
@@ -110,8 +222,39 @@ mod tests {
             env::set_var("USER_TOKEN_TTL", "3600");
             env::set_var("SHORTLINK_MIN_TTL", "60");
             env::set_var("SHORTLINK_MAX_TTL", "3600");
+            env::set_var("SHORTLINK_ALLOW_PERMANENT", "true");
+            env::set_var("SHORTLINK_BATCH_MAX_ITEMS", "500");
+            env::set_var("SHORT_CODE_STRATEGY", "feistel");
+            env::set_var("SHORT_CODE_LENGTH", "7");
+            env::set_var(
+                "SHORT_CODE_ALPHABET",
+                "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+            );
+            env::set_var("SHORT_CODE_SECRET", "secret");
+            env::set_var(
+                "CUSTOM_CODE_ALPHABET",
+                "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_",
+            );
+            env::set_var("CUSTOM_CODE_MIN_LEN", "4");
+            env::set_var("CUSTOM_CODE_MAX_LEN", "32");
+            env::set_var("SHORT_CODE_RESERVED_FILE", "config/reserved_words.txt");
+            env::set_var("SHORT_CODE_PROFANITY_FILE", "");
+            env::set_var("URL_SORT_QUERY_PARAMS", "true");
+            env::set_var("URL_STRIP_QUERY_PARAMS", "utm_*,fbclid");
+            env::set_var("DESTINATION_ALLOWED_SCHEMES", "http,https");
+            env::set_var("DESTINATION_BLOCKLIST_FILE", "config/blocked_domains.txt");
+            env::set_var("DESTINATION_BLOCKLIST_RELOAD_INTERVAL", "30");
+            env::set_var("SHORTLINK_SELF_HOSTS", "sho.rt");
+            env::set_var("SHORTLINK_EXPIRY_GRACE_PERIOD", "604800");
+            env::set_var("LINK_NOT_ACTIVE_STATUS", "404");
+            env::set_var("LINK_NOT_ACTIVE_MESSAGE", "Link is not active yet");
+            env::set_var("LINK_UNLOCK_TTL", "3600");
+            env::set_var("LINK_UNLOCK_COOKIE_SECURE", "false");
+            env::set_var("LINK_UNLOCK_FAIL_LIMIT", "5");
+            env::set_var("LINK_UNLOCK_FAIL_WINDOW", "900");
             env::set_var("REDIS_MAX_TTL", "86400");
             env::set_var("REDIS_MIN_CACHE_TTL", "60");
+            env::set_var("CACHE_DOUBLE_DELETE_DELAY_MS", "1000");
             env::set_var("MAX_STATS_DAYS", "30");
             env::set_var("IP_RATE_LIMIT", "100");
             env::set_var("IP_RATE_LIMIT_WINDOW", "60");
@@ -123,6 +266,34 @@ mod tests {
             env::set_var("IP_REGISTER_TTL", "86400");
             env::set_var("USER_RATE_LIMIT", "200");
             env::set_var("USER_RATE_LIMIT_WINDOW", "60");
+            env::set_var("USER_TOKEN_LIMIT", "5");
+            env::set_var("GLOBAL_TIMEOUT_MS", "5000");
+            env::set_var("MYSQL_MAX_CONNECTIONS", "10");
+            env::set_var("MYSQL_ACQUIRE_TIMEOUT_MS", "3000");
+            env::set_var("MYSQL_QUERY_TIMEOUT_MS", "3000");
+            env::set_var("MYSQL_LOCK_WAIT_TIMEOUT_S", "5");
+            env::set_var("RUN_MIGRATIONS", "false");
+            env::set_var("REDIS_POOL_SIZE", "16");
+            env::set_var("REDIS_TIMEOUT_WAIT_MS", "1000");
+            env::set_var("REDIS_TIMEOUT_CREATE_MS", "1000");
+            env::set_var("REDIS_TIMEOUT_RECYCLE_MS", "1000");
+            env::set_var("BG_REDIS_QUEUE_CAP", "10000");
+            env::set_var("BG_REDIS_MAX_CONCURRENCY", "32");
+            env::set_var("BG_SPILL_CAP", "50000");
+            env::set_var("BG_SPILL_FLUSH_INTERVAL_MS", "200");
+            env::set_var("SHUTDOWN_TIMEOUT_MS", "10000");
+            env::set_var("BG_EXPIRED_LINKS_SYNC_INTERVAL", "3600");
+            env::set_var("BG_CLICK_COUNTS_SYNC_INTERVAL", "60");
+            env::set_var("BG_VISIT_LOGS_SYNC_INTERVAL", "30");
+            env::set_var("BG_PURGE_CHUNK_SIZE", "500");
+            env::set_var("BG_PURGE_MAX_CHUNKS", "100");
+            env::set_var("BG_PURGE_LOG_BATCH_SIZE", "5000");
+            env::set_var("BG_JOBS_MAX_JITTER_MS", "5000");
+            env::set_var("BG_JOB_LEASE_TTL_MS", "30000");
+            env::set_var("VISIT_LOG_SYNC_BATCH_SIZE", "500");
+            env::set_var("VISIT_LOG_INSERT_MAX_ROWS", "200");
+            env::set_var("VISIT_LOG_CLAIM_IDLE_MS", "60000");
+            env::set_var("VISIT_LOG_MAX_DELIVERIES", "5");
         }
 
         let cfg = AppConfig::from_env().expect("load config");
@@ -136,5 +307,43 @@ mod tests {
         assert_eq!(cfg.user_rate_limit_window, 60);
         assert_eq!(cfg.ip_register_limit, 5);
         assert_eq!(cfg.user_rate_limit, 200);
+        assert!(!cfg.run_migrations);
+        assert!(cfg.shortlink_allow_permanent);
+        assert_eq!(cfg.short_code_strategy, "feistel");
+        assert_eq!(cfg.short_code_length, 7);
+        assert_eq!(cfg.custom_code_max_len, 32);
+        assert_eq!(cfg.url_strip_query_params, "utm_*,fbclid");
+        assert_eq!(cfg.destination_blocklist_reload_interval, 30);
+        assert_eq!(cfg.shortlink_expiry_grace_period, 604800);
+        assert_eq!(cfg.link_not_active_status, 404);
+        assert_eq!(cfg.link_unlock_fail_limit, 5);
+
+        unsafe {
+            env::set_var("BG_JOB_LEASE_TTL_MS", "2");
+        }
+        assert!(AppConfig::from_env().is_err());
+        unsafe {
+            env::set_var("BG_JOB_LEASE_TTL_MS", "30000");
+            env::set_var("LINK_NOT_ACTIVE_STATUS", "500");
+        }
+        assert!(AppConfig::from_env().is_err());
+        unsafe {
+            env::set_var("LINK_NOT_ACTIVE_STATUS", "404");
+            env::set_var("BG_PURGE_CHUNK_SIZE", "0");
+        }
+        assert!(AppConfig::from_env().is_err());
+        unsafe {
+            env::set_var("BG_PURGE_CHUNK_SIZE", "500");
+            env::set_var("BG_PURGE_LOG_BATCH_SIZE", "0");
+        }
+        assert!(AppConfig::from_env().is_err());
+        unsafe {
+            env::set_var("BG_PURGE_LOG_BATCH_SIZE", "5000");
+            env::set_var("SHORTLINK_EXPIRY_GRACE_PERIOD", "-1");
+        }
+        assert!(AppConfig::from_env().is_err());
+        unsafe {
+            env::set_var("SHORTLINK_EXPIRY_GRACE_PERIOD", "604800");
+        }
     }
 }
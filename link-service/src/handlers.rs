@@ -1,19 +1,26 @@
 use axum::{
     Extension, Json,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Redirect,
+    extract::{Form, Path, Query, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
 };
 use axum_extra::TypedHeader;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::Tz;
-use headers::{Referer, UserAgent};
+use headers::{Cookie, Referer, UserAgent};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::warn;
 use validator::{Validate, ValidationError};
 
-use crate::{models::LinkView, services::shortlinks::ShortlinkService, state::AppState};
+use crate::{
+    models::{LinkRevisionView, LinkView, VIEW_TIME_FORMAT},
+    services::{
+        link_password::unlock_cookie_name,
+        shortlinks::{NewShortlink, RedirectOutcome, ShortlinkService},
+    },
+    state::AppState,
+};
 
 /// 客户端请求：创建短链
 #[derive(Deserialize, Validate)]
@@ -21,13 +28,81 @@ pub struct ShortlinkCreateReq {
     #[validate(url(message = "Invalid URL"))]
     pub url: String,
     pub ttl: Option<i64>,
+    /// 过期时间（`timezone` 时区的本地时间），与 `ttl` 互斥
+    pub expire_at: Option<NaiveDateTime>,
     pub short_code: Option<String>,
+    /// 生效时间（`timezone` 时区的本地时间），之前访问返回"尚未生效"
+    pub activate_at: Option<NaiveDateTime>,
+    /// `expire_at`、`activate_at` 所在时区（IANA 时区名称），默认 UTC
+    #[serde(default = "default_timezone")]
+    #[validate(custom(function = "validate_tz"))]
+    pub timezone: String,
+    /// 永久有效（不过期），与 `ttl` 互斥，需配置 `shortlink_allow_permanent` 开启
+    #[serde(default)]
+    pub permanent: bool,
+    /// 跳转次数上限，次数用完后访问返回 410；为 1 时即一次性短链
+    #[validate(range(min = 1, message = "max_clicks must be at least 1"))]
+    pub max_clicks: Option<u32>,
+    /// 闲置天数：超过该天数没有点击（从未点击则从创建时起算）即过期，与 `ttl`/`expire_at` 同时生效
+    #[validate(range(
+        min = 1,
+        max = 3650,
+        message = "inactivity_days must be between 1 and 3650"
+    ))]
+    pub inactivity_days: Option<u16>,
+    /// 已有指向同一目标地址、有效期相同的未过期短链时直接复用，仅对单条创建且未指定 `short_code`、`activate_at`、`inactivity_days`、`max_clicks`、`password`、`expired_landing_url` 时生效
+    #[serde(default)]
+    pub reuse_existing: bool,
+    /// 访问密码，设置后访问短链需先输入密码解锁
+    #[validate(length(
+        min = 4,
+        max = 128,
+        message = "Password must be between 4 and 128 characters"
+    ))]
+    pub password: Option<String>,
+    /// 过期落地页：过期后宽限期内访问返回 410 页面并引导到该地址
+    #[validate(url(message = "Invalid expired_landing_url"))]
+    pub expired_landing_url: Option<String>,
 }
 
 /// 服务端返回：短链创建结果
 #[derive(Serialize)]
 pub struct ShortlinkCreateResp {
     pub short_url: String,
+    /// 是否复用了已有短链
+    pub reused: bool,
+    /// 过期时间（UTC），永久有效为 `None`
+    pub expire_at: Option<String>,
+    /// 过期时间在请求 `timezone` 时区的本地形式（RFC 3339，带偏移）
+    pub expire_at_local: Option<String>,
+}
+
+/// 客户端请求：批量创建短链
+#[derive(Deserialize)]
+pub struct ShortlinkBatchCreateReq {
+    pub items: Vec<ShortlinkCreateReq>,
+}
+
+/// 批量创建中单条的结果，`index` 对应请求中的下标；成功时字段与单条创建的返回相同
+#[derive(Serialize)]
+pub struct ShortlinkBatchItemResp {
+    pub index: usize,
+    #[serde(flatten)]
+    pub created: Option<ShortlinkCreateResp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 服务端返回：批量创建结果
+#[derive(Serialize)]
+pub struct ShortlinkBatchCreateResp {
+    pub results: Vec<ShortlinkBatchItemResp>,
+}
+
+/// 解锁表单：密码保护短链
+#[derive(Deserialize)]
+pub struct LinkUnlockReq {
+    pub password: String,
 }
 
 /// 默认时区
@@ -47,12 +122,17 @@ fn validate_tz(timezone: &str) -> Result<(), ValidationError> {
 #[derive(Debug, Default, Deserialize, Validate)]
 pub struct LinkQuery {
     // ---筛选条件---
-    pub user_id: Option<u64>,             // 用户ID
-    pub short_code: Option<String>,       // 短码
-    pub long_url: Option<String>,         // 长 URL
-    pub click_count: Option<u64>,         // 点击量
+    pub user_id: Option<u64>,       // 用户ID
+    pub short_code: Option<String>, // 短码
+    pub long_url: Option<String>,   // 长 URL
+    #[serde(skip)]
+    pub canonical_url: Option<String>, // long_url 为完整 URL 时的规范形式，由服务端填充
+    pub click_count: Option<u64>,   // 点击量
     pub date_from: Option<NaiveDateTime>, // 日期范围
     pub date_to: Option<NaiveDateTime>,
+    /// 是否包含已过期、仍在宽限期内的短链（默认只返回未过期的）
+    #[serde(default)]
+    pub include_expired: bool,
     /// 客户端所在时区（使用 IANA 时区名称，如 "Asia/Shanghai"）。
     /// 该参数用于将前端传入的本地时间范围转换为 UTC 时间进行后端查询。
     /// 如果未传此参数，后端默认按照 UTC 查询，可能导致跨时区用户的查询结果不准确。
@@ -86,6 +166,51 @@ pub struct DeleteLinksReq {
     pub ids: Vec<u64>,
 }
 
+/// 编辑短链请求，至少修改一项
+#[derive(Deserialize, Validate)]
+pub struct LinkUpdateReq {
+    #[validate(url(message = "Invalid URL"))]
+    pub url: Option<String>,
+    /// 新的有效期（秒，从当前时间起算）
+    pub ttl: Option<i64>,
+    /// 新的过期时间（`timezone` 时区的本地时间），与 `ttl` 互斥
+    pub expire_at: Option<NaiveDateTime>,
+    /// `expire_at` 所在时区（IANA 时区名称），默认 UTC；也用于返回本地化的过期时间
+    #[serde(default = "default_timezone")]
+    #[validate(custom(function = "validate_tz"))]
+    pub timezone: String,
+    /// 改为永久有效，与 `ttl`、`expire_at` 互斥
+    #[serde(default)]
+    pub permanent: bool,
+    /// 新的过期落地页，空字符串为清除
+    pub expired_landing_url: Option<String>,
+}
+
+/// 重新激活过期短链请求；已到过期时间的短链必须指定新的有效期
+#[derive(Deserialize, Validate)]
+pub struct LinkReactivateReq {
+    /// 新的有效期（秒，从当前时间起算）
+    pub ttl: Option<i64>,
+    /// 新的过期时间（`timezone` 时区的本地时间），与 `ttl` 互斥
+    pub expire_at: Option<NaiveDateTime>,
+    /// `expire_at` 所在时区（IANA 时区名称），默认 UTC；也用于返回本地化的过期时间
+    #[serde(default = "default_timezone")]
+    #[validate(custom(function = "validate_tz"))]
+    pub timezone: String,
+    /// 改为永久有效，与 `ttl`、`expire_at` 互斥
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+/// 服务端返回：编辑后的短链
+#[derive(Serialize)]
+pub struct LinkUpdateResp {
+    #[serde(flatten)]
+    pub link: LinkView,
+    /// 过期时间在请求 `timezone` 时区的本地形式（RFC 3339，带偏移）
+    pub expire_at_local: Option<String>,
+}
+
 /// 点击量统计（按天）
 #[derive(Debug, Deserialize, Validate)]
 pub struct LinkStatsQuery {
@@ -103,6 +228,147 @@ fn default_days() -> u8 {
     30
 }
 
+/// 把 `timezone` 时区的本地时间转换为 UTC，本地时间不存在或有歧义（夏令时切换）时返回 400
+fn local_to_utc(
+    timezone: &str,
+    dt: NaiveDateTime,
+    action: &str,
+) -> Result<DateTime<Utc>, (StatusCode, String)> {
+    let tz: Tz = timezone.parse().map_err(|_| {
+        warn!("{}: 无法解析时区: {}", action, timezone);
+        (StatusCode::BAD_REQUEST, "Invalid timezone".into())
+    })?;
+
+    tz.from_local_datetime(&dt)
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| {
+            warn!("{}: 本地时间无法唯一映射: dt={} tz={}", action, dt, tz);
+            (
+                StatusCode::BAD_REQUEST,
+                "Date time is ambiguous or invalid for timezone".into(),
+            )
+        })
+}
+
+/// UTC 时间在 `timezone` 时区的本地形式（RFC 3339，带偏移）
+fn to_local_rfc3339(t: DateTime<Utc>, timezone: &str) -> String {
+    let tz: Tz = timezone.parse().unwrap_or(Tz::UTC);
+    t.with_timezone(&tz).to_rfc3339()
+}
+
+/// 校验生效时间，返回 UTC 时间；必须早于过期时间
+fn resolve_activate_at(
+    activate_at: Option<NaiveDateTime>,
+    timezone: &str,
+    expire_at: Option<DateTime<Utc>>,
+    user_id: u64,
+    action: &str,
+) -> Result<Option<DateTime<Utc>>, (StatusCode, String)> {
+    let Some(local) = activate_at else {
+        return Ok(None);
+    };
+    let activate_at = local_to_utc(timezone, local, action)?;
+
+    if expire_at.is_some_and(|expire_at| activate_at >= expire_at) {
+        warn!(
+            "{}: 生效时间不早于过期时间: user_id={}, activate_at={}, expire_at={:?}",
+            action, user_id, activate_at, expire_at
+        );
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "activate_at must be before the expiry time".into(),
+        ));
+    }
+
+    Ok(Some(activate_at))
+}
+
+/// 校验过期时间参数
+///
+/// `expire_at` 为 `timezone` 时区的本地时间，转换为 UTC 后按剩余秒数同样受
+/// `[shortlink_min_ttl, shortlink_max_ttl]` 限制，与 `ttl`、`permanent` 互斥。
+/// 返回 `None` 表示未指定，`Some(None)` 表示永久有效，`Some(Some(t))` 为过期时间（UTC）。
+async fn resolve_expiry(
+    state: &AppState,
+    ttl: Option<i64>,
+    expire_at: Option<NaiveDateTime>,
+    timezone: &str,
+    permanent: bool,
+    user_id: u64,
+    action: &str,
+) -> Result<Option<Option<DateTime<Utc>>>, (StatusCode, String)> {
+    let now = Utc::now();
+    let Some(local) = expire_at else {
+        let ttl = resolve_ttl(state, ttl, permanent, user_id, action).await?;
+        return Ok(ttl.map(|ttl| ttl.map(|ttl| now + chrono::Duration::seconds(ttl))));
+    };
+
+    if ttl.is_some() {
+        warn!("{}: ttl 与 expire_at 同时传入: user_id={}", action, user_id);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "ttl and expire_at must not both be set".into(),
+        ));
+    }
+    let expire_at = local_to_utc(timezone, local, action)?;
+    let remaining = (expire_at - now).num_seconds();
+    resolve_ttl(state, Some(remaining), permanent, user_id, action).await?;
+
+    Ok(Some(Some(expire_at)))
+}
+
+/// 校验有效期参数
+///
+/// 返回 `None` 表示未指定，`Some(None)` 表示永久有效，`Some(Some(ttl))` 为有效秒数。
+async fn resolve_ttl(
+    state: &AppState,
+    ttl: Option<i64>,
+    permanent: bool,
+    user_id: u64,
+    action: &str,
+) -> Result<Option<Option<i64>>, (StatusCode, String)> {
+    let config = state.config.read().await;
+
+    // 永久短链
+    if permanent {
+        if ttl.is_some() {
+            warn!("{}: permanent 与 ttl 同时传入: user_id={}", action, user_id);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "ttl must not be set for permanent link".into(),
+            ));
+        }
+        if !config.shortlink_allow_permanent {
+            warn!("{}: 未开放永久短链: user_id={}", action, user_id);
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Permanent links are not allowed".into(),
+            ));
+        }
+        return Ok(Some(None));
+    }
+
+    let Some(ttl) = ttl else {
+        return Ok(None);
+    };
+
+    let min_ttl = config.shortlink_min_ttl;
+    let max_ttl = config.shortlink_max_ttl;
+    if ttl < min_ttl || ttl > max_ttl {
+        warn!(
+            "{}: TTL越界: user_id={}, ttl={}, min={}, max={}",
+            action, user_id, ttl, min_ttl, max_ttl
+        );
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("TTL must be between {} and {}", min_ttl, max_ttl),
+        ));
+    }
+
+    Ok(Some(Some(ttl)))
+}
+
 /// 创建短链
 pub async fn create(
     State(state): State<Arc<AppState>>,
@@ -118,49 +384,317 @@ pub async fn create(
         return Err((StatusCode::BAD_REQUEST, format!("Validation error: {}", e)));
     }
 
-    // 校验短链有效时间
-    let config = state.config.read().await;
-    let min_ttl = config.shortlink_min_ttl;
-    let max_ttl = config.shortlink_max_ttl;
+    // 校验自定义短码
+    if let Some(code) = &payload.short_code {
+        state.code_policy.check(code)?;
+    }
+
+    // 校验短链有效时间，未指定时使用最短有效期
+    let min_ttl = state.config.read().await.shortlink_min_ttl;
+    let expire_at = resolve_expiry(
+        &state,
+        payload.ttl,
+        payload.expire_at,
+        &payload.timezone,
+        payload.permanent,
+        user_id,
+        "create_shortlink",
+    )
+    .await?
+    .unwrap_or_else(|| Some(Utc::now() + chrono::Duration::seconds(min_ttl)));
+
+    let activate_at = resolve_activate_at(
+        payload.activate_at,
+        &payload.timezone,
+        expire_at,
+        user_id,
+        "create_shortlink",
+    )?;
+    let canonical_url = ShortlinkService::resolve_destination(&state, &payload.url)?;
+    if let Some(landing_url) = &payload.expired_landing_url {
+        ShortlinkService::resolve_destination(&state, landing_url)?;
+    }
+
+    // 创建短链
+    let created = ShortlinkService::create_shortlink(
+        &state,
+        NewShortlink {
+            long_url: payload.url,
+            canonical_url,
+            short_code: payload.short_code,
+            expire_at,
+            activate_at,
+            inactivity_days: payload.inactivity_days,
+            max_clicks: payload.max_clicks,
+            password: payload.password,
+            expired_landing_url: payload.expired_landing_url,
+        },
+        payload.reuse_existing,
+        user_id,
+    )
+    .await?;
 
-    let ttl = match payload.ttl {
-        Some(ttl) => {
-            if ttl < min_ttl || ttl > max_ttl {
-                warn!(
-                    "create_shortlink: TTL越界: user_id={}, ttl={}, min={}, max={}",
-                    user_id, ttl, min_ttl, max_ttl
-                );
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    format!("TTL must be between {} and {}", min_ttl, max_ttl),
-                ));
+    Ok(Json(ShortlinkCreateResp {
+        short_url: created.short_url,
+        reused: created.reused,
+        expire_at: created
+            .expire_at
+            .map(|t| t.format(VIEW_TIME_FORMAT).to_string()),
+        expire_at_local: created
+            .expire_at
+            .map(|t| to_local_rfc3339(t, &payload.timezone)),
+    }))
+}
+
+/// 批量创建短链
+///
+/// 单条参数错误或自定义短码冲突只影响该条，其余条目在同一事务内创建。
+pub async fn create_batch(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<u64>,
+    Json(payload): Json<ShortlinkBatchCreateReq>,
+) -> Result<Json<ShortlinkBatchCreateResp>, (StatusCode, String)> {
+    let (max_items, min_ttl) = {
+        let config = state.config.read().await;
+        (config.shortlink_batch_max_items, config.shortlink_min_ttl)
+    };
+    if payload.items.is_empty() || payload.items.len() > max_items {
+        warn!(
+            "create_batch: 条数越界: user_id={}, items={}, max={}",
+            user_id,
+            payload.items.len(),
+            max_items
+        );
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Items must be between 1 and {}", max_items),
+        ));
+    }
+
+    // 逐条校验，通过的条目记下原始下标
+    let mut results: Vec<ShortlinkBatchItemResp> = Vec::with_capacity(payload.items.len());
+    let mut valid = Vec::new();
+    // 通过校验条目的 (下标, 过期时间, 时区)，用于组装返回
+    let mut valid_meta = Vec::new();
+    for (index, item) in payload.items.into_iter().enumerate() {
+        let checked = match item.validate() {
+            Ok(()) => match item
+                .short_code
+                .as_deref()
+                .map(|c| state.code_policy.check(c))
+            {
+                Some(Err(e)) => Err(e),
+                // 未指定有效期时使用最短有效期
+                _ => resolve_expiry(
+                    &state,
+                    item.ttl,
+                    item.expire_at,
+                    &item.timezone,
+                    item.permanent,
+                    user_id,
+                    "create_batch",
+                )
+                .await
+                .map(|expire_at| {
+                    expire_at
+                        .unwrap_or_else(|| Some(Utc::now() + chrono::Duration::seconds(min_ttl)))
+                }),
+            },
+            Err(e) => Err((StatusCode::BAD_REQUEST, format!("Validation error: {}", e))),
+        };
+        let checked = checked.and_then(|expire_at| {
+            let activate_at = resolve_activate_at(
+                item.activate_at,
+                &item.timezone,
+                expire_at,
+                user_id,
+                "create_batch",
+            )?;
+            let canonical_url = ShortlinkService::resolve_destination(&state, &item.url)?;
+            if let Some(landing_url) = &item.expired_landing_url {
+                ShortlinkService::resolve_destination(&state, landing_url)?;
             }
-            ttl
+            Ok((expire_at, activate_at, canonical_url))
+        });
+        match checked {
+            Ok((expire_at, activate_at, canonical_url)) => {
+                valid_meta.push((index, expire_at, item.timezone));
+                valid.push(NewShortlink {
+                    long_url: item.url,
+                    canonical_url,
+                    short_code: item.short_code,
+                    expire_at,
+                    activate_at,
+                    inactivity_days: item.inactivity_days,
+                    max_clicks: item.max_clicks,
+                    password: item.password,
+                    expired_landing_url: item.expired_landing_url,
+                });
+            }
+            Err((_, error)) => results.push(ShortlinkBatchItemResp {
+                index,
+                created: None,
+                error: Some(error),
+            }),
         }
-        None => min_ttl,
-    };
+    }
 
-    // 创建短链
-    let short_url =
-        ShortlinkService::create_shortlink(&state, &payload.url, payload.short_code, ttl, user_id)
-            .await?;
+    if !valid.is_empty() {
+        let created = ShortlinkService::create_shortlinks_batch(&state, valid, user_id).await?;
+        for ((index, expire_at, timezone), res) in valid_meta.into_iter().zip(created) {
+            results.push(match res {
+                Ok(short_url) => ShortlinkBatchItemResp {
+                    index,
+                    created: Some(ShortlinkCreateResp {
+                        short_url,
+                        reused: false,
+                        expire_at: expire_at.map(|t| t.format(VIEW_TIME_FORMAT).to_string()),
+                        expire_at_local: expire_at.map(|t| to_local_rfc3339(t, &timezone)),
+                    }),
+                    error: None,
+                },
+                Err((_, error)) => ShortlinkBatchItemResp {
+                    index,
+                    created: None,
+                    error: Some(error),
+                },
+            });
+        }
+    }
+    results.sort_by_key(|r| r.index);
+
+    Ok(Json(ShortlinkBatchCreateResp { results }))
+}
+
+/// 转义 HTML 特殊字符
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 密码保护短链的解锁页面，表单提交到当前地址
+fn unlock_page(status: StatusCode, short_code: &str, error: Option<&str>) -> Response {
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>", escape_html(e)))
+        .unwrap_or_default();
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{code}</title>
+</head>
+<body>
+<h1>This link is password protected</h1>
+{error}<form method="post">
+<input type="password" name="password" placeholder="Password" autofocus required>
+<button type="submit">Unlock</button>
+</form>
+</body>
+</html>
+"#,
+        code = escape_html(short_code),
+        error = error,
+    );
+    (status, Html(body)).into_response()
+}
 
-    Ok(Json(ShortlinkCreateResp { short_url }))
+/// 过期短链页面（410），设置了落地页时自动跳转过去
+fn expired_page(short_code: &str, landing_url: Option<&str>) -> Response {
+    let landing = landing_url
+        .map(|url| {
+            let url = escape_html(url);
+            (
+                format!("<meta http-equiv=\"refresh\" content=\"3; url={}\">\n", url),
+                format!("<p><a href=\"{}\">{}</a></p>\n", url, url),
+            )
+        })
+        .unwrap_or_default();
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+{refresh}<title>{code}</title>
+</head>
+<body>
+<h1>This link has expired</h1>
+{link}</body>
+</html>
+"#,
+        code = escape_html(short_code),
+        refresh = landing.0,
+        link = landing.1,
+    );
+    (StatusCode::GONE, Html(body)).into_response()
 }
 
 /// 重定向
+///
+/// 有密码的短链未解锁时返回解锁页面；过期但仍在宽限期内时返回 410 页面。
 pub async fn redirect(
     Extension(ip): Extension<String>,
     TypedHeader(user_agent): TypedHeader<UserAgent>,
     referer: Option<TypedHeader<Referer>>,
+    cookie: Option<TypedHeader<Cookie>>,
     Path(short_code): Path<String>,
     State(state): State<Arc<AppState>>,
-) -> Result<Redirect, (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     let ua = user_agent.as_str();
     let ref_ = referer.map(|r| r.to_string()).unwrap_or_default();
-    let long_url = ShortlinkService::get_long_url(&ip, ua, &ref_, &state, &short_code).await?;
+    let cookie_name = unlock_cookie_name(&short_code);
+    let unlock_token = cookie.as_ref().and_then(|c| c.get(&cookie_name));
+    let outcome =
+        ShortlinkService::get_long_url(&ip, ua, &ref_, &state, &short_code, unlock_token).await?;
+
+    Ok(match outcome {
+        RedirectOutcome::Redirect(long_url) => Redirect::to(&long_url).into_response(),
+        RedirectOutcome::PasswordRequired => unlock_page(StatusCode::OK, &short_code, None),
+        RedirectOutcome::Expired { landing_url } => {
+            expired_page(&short_code, landing_url.as_deref())
+        }
+    })
+}
+
+/// 解锁密码保护短链
+///
+/// 密码正确时写入解锁 Cookie 并重定向回短链；Cookie 不设 `Path`，默认作用于短链所在目录，
+/// Location 用相对地址，经反向代理加前缀后仍然有效。
+pub async fn unlock(
+    Extension(ip): Extension<String>,
+    Path(short_code): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Form(payload): Form<LinkUnlockReq>,
+) -> Result<Response, (StatusCode, String)> {
+    let token =
+        match ShortlinkService::unlock_link(&state, &ip, &short_code, &payload.password).await {
+            Ok(token) => token,
+            Err((status @ (StatusCode::UNAUTHORIZED | StatusCode::TOO_MANY_REQUESTS), msg)) => {
+                return Ok(unlock_page(status, &short_code, Some(&msg)));
+            }
+            Err(e) => return Err(e),
+        };
 
-    Ok(Redirect::to(&long_url))
+    let (ttl, secure) = {
+        let config = state.config.read().await;
+        (config.link_unlock_ttl, config.link_unlock_cookie_secure)
+    };
+    let cookie = format!(
+        "{}={}; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        unlock_cookie_name(&short_code),
+        token,
+        ttl,
+        if secure { "; Secure" } else { "" }
+    );
+
+    Ok(([(header::SET_COOKIE, cookie)], Redirect::to(&short_code)).into_response())
 }
 
 /// 获取短链列表
@@ -179,33 +713,18 @@ pub async fn list_links(
     }
 
     q.user_id = Some(user_id);
+    q.canonical_url = q
+        .long_url
+        .as_deref()
+        .filter(|url| url.contains("://"))
+        .and_then(|url| state.url_canonicalizer.canonicalize(url).ok());
 
     // 将本地时间范围转换为 UTC，避免跨时区查询偏差
-    if q.date_from.is_some() || q.date_to.is_some() {
-        let tz: Tz = q.timezone.parse().map_err(|_| {
-            warn!("list_links: 无法解析时区: {}", q.timezone);
-            (StatusCode::BAD_REQUEST, "Invalid timezone".into())
-        })?;
-
-        let mut convert_local = |dt: NaiveDateTime| -> Result<NaiveDateTime, (StatusCode, String)> {
-            tz.from_local_datetime(&dt)
-                .single()
-                .map(|dt| dt.with_timezone(&Utc).naive_utc())
-                .ok_or_else(|| {
-                    warn!("list_links: 本地时间无法唯一映射: dt={} tz={}", dt, tz);
-                    (
-                        StatusCode::BAD_REQUEST,
-                        "Date time is ambiguous or invalid for timezone".into(),
-                    )
-                })
-        };
-
-        if let Some(local_from) = q.date_from {
-            q.date_from = Some(convert_local(local_from)?);
-        }
-        if let Some(local_to) = q.date_to {
-            q.date_to = Some(convert_local(local_to)?);
-        }
+    if let Some(local_from) = q.date_from {
+        q.date_from = Some(local_to_utc(&q.timezone, local_from, "list_links")?.naive_utc());
+    }
+    if let Some(local_to) = q.date_to {
+        q.date_to = Some(local_to_utc(&q.timezone, local_to, "list_links")?.naive_utc());
     }
 
     let (links, count) = ShortlinkService::list_links(&state, &q, q.limit, q.offset).await?;
@@ -232,6 +751,123 @@ pub async fn delete_links(
     Ok(())
 }
 
+/// 编辑短链
+pub async fn update_link(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<u64>,
+    Path(id): Path<u64>,
+    Json(payload): Json<LinkUpdateReq>,
+) -> Result<Json<LinkUpdateResp>, (StatusCode, String)> {
+    if let Err(e) = payload.validate() {
+        warn!(
+            "update_link: 参数校验失败: user_id={}, id={}, error={}",
+            user_id, id, e
+        );
+        return Err((StatusCode::BAD_REQUEST, format!("Validation error: {}", e)));
+    }
+
+    let expire_at = resolve_expiry(
+        &state,
+        payload.ttl,
+        payload.expire_at,
+        &payload.timezone,
+        payload.permanent,
+        user_id,
+        "update_link",
+    )
+    .await?;
+    if payload.url.is_none() && expire_at.is_none() && payload.expired_landing_url.is_none() {
+        warn!(
+            "update_link: 未指定修改内容: user_id={}, id={}",
+            user_id, id
+        );
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Nothing to update: require url, ttl, expire_at, permanent or expired_landing_url"
+                .into(),
+        ));
+    }
+
+    let link = ShortlinkService::update_link(
+        &state,
+        id,
+        user_id,
+        payload.url.as_deref(),
+        expire_at,
+        payload.expired_landing_url.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(link_update_resp(link, &payload.timezone)))
+}
+
+/// 返回的 expire_at 为 UTC，另附 `timezone` 时区的本地形式
+fn link_update_resp(link: LinkView, timezone: &str) -> LinkUpdateResp {
+    let expire_at_local = link
+        .expire_at
+        .as_deref()
+        .and_then(|t| NaiveDateTime::parse_from_str(t, VIEW_TIME_FORMAT).ok())
+        .map(|t| to_local_rfc3339(t.and_utc(), timezone));
+
+    LinkUpdateResp {
+        link,
+        expire_at_local,
+    }
+}
+
+/// 重新激活宽限期内的过期短链
+pub async fn reactivate_link(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<u64>,
+    Path(id): Path<u64>,
+    Json(payload): Json<LinkReactivateReq>,
+) -> Result<Json<LinkUpdateResp>, (StatusCode, String)> {
+    if let Err(e) = payload.validate() {
+        warn!(
+            "reactivate_link: 参数校验失败: user_id={}, id={}, error={}",
+            user_id, id, e
+        );
+        return Err((StatusCode::BAD_REQUEST, format!("Validation error: {}", e)));
+    }
+
+    let expire_at = resolve_expiry(
+        &state,
+        payload.ttl,
+        payload.expire_at,
+        &payload.timezone,
+        payload.permanent,
+        user_id,
+        "reactivate_link",
+    )
+    .await?;
+
+    let link = ShortlinkService::reactivate_link(&state, id, user_id, expire_at).await?;
+
+    Ok(Json(link_update_resp(link, &payload.timezone)))
+}
+
+/// 目标地址修改记录
+pub async fn link_history(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<u64>,
+    Path(id): Path<u64>,
+) -> Result<Json<Vec<LinkRevisionView>>, (StatusCode, String)> {
+    let revisions = ShortlinkService::link_history(&state, id, user_id).await?;
+
+    Ok(Json(revisions))
+}
+
+/// 回滚目标地址
+pub async fn rollback_link(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<u64>,
+    Path((id, revision)): Path<(u64, u32)>,
+) -> Result<Json<LinkView>, (StatusCode, String)> {
+    let link = ShortlinkService::rollback_link(&state, id, user_id, revision).await?;
+
+    Ok(Json(link))
+}
+
 /// 点击量统计（按天）
 pub async fn get_link_stats(
     State(state): State<Arc<AppState>>,
@@ -1,13 +1,18 @@
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{get, patch, post},
+};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
 };
-use std::{net::SocketAddr, sync::Arc};
 use tokio::{
     net::TcpListener,
-    sync::{RwLock, mpsc::channel},
+    sync::{RwLock, mpsc::channel, watch},
 };
 use tower_http::trace::TraceLayer;
+use tracing::{info, warn};
 use tracing_subscriber::fmt::time::LocalTime;
 
 use common::db;
@@ -17,10 +22,20 @@ use link_service::middleware::{
     auth::jwt_auth, ip_rate_limiter::ip_rate_limiter, real_ip_layer::real_ip_layer,
     user_rate_limiter::user_rate_limiter,
 };
-use link_service::state::AppState;
+use link_service::models::Link;
+use link_service::state::{AppState, OverflowStats};
 use link_service::{
     config::AppConfig,
-    services::background_jobs::{BackgroundJob, spawn_background_workers},
+    services::{
+        background_jobs::{
+            BackgroundJob, spawn_background_workers, spawn_blocklist_reloader, spawn_scheduler,
+            spawn_spill_flusher,
+        },
+        code_policy::CodePolicy,
+        destination_policy::DestinationPolicy,
+        short_code,
+        url_normalize::UrlCanonicalizer,
+    },
 };
 
 #[tokio::main]
@@ -42,6 +57,14 @@ async fn main() {
     .await
     .unwrap();
 
+    // 按需执行数据库迁移
+    if cfg.run_migrations {
+        db::run_migrations(&mysql_pool)
+            .await
+            .expect("run database migrations");
+        info!("database migrations applied");
+    }
+
     let redis_pool = db::new_redis_pool(
         &cfg.redis_url,
         cfg.redis_pool_size,
@@ -57,18 +80,69 @@ async fn main() {
     let (tx, rx) = channel::<BackgroundJob>(cfg.bg_redis_queue_cap);
     let bg_redis_max_concurrency = cfg.bg_redis_max_concurrency;
 
+    // 副本标识：容器内 HOSTNAME 即容器 ID，再加上进程号和随机后缀
+    let instance_id = format!(
+        "{}:{}:{:08x}",
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "link-service".into()),
+        std::process::id(),
+        rand::random::<u32>()
+    );
+
+    let code_generator = short_code::from_config(&cfg).expect("short code generator");
+    let code_policy = CodePolicy::from_config(&cfg).expect("short code policy");
+    let url_canonicalizer = UrlCanonicalizer::from_config(&cfg);
+    let destination_policy = DestinationPolicy::from_config(&cfg).expect("destination policy");
+
     let state = Arc::new(AppState {
         mysql_pool,
         redis_pool,
         config: RwLock::new(cfg),
         bg_jobs_tx: tx,
         pending_set: DashSet::new(),
+        bg_spill: Mutex::new(VecDeque::new()),
+        overflow_stats: OverflowStats::default(),
+        instance_id,
+        code_generator,
+        code_policy,
+        url_canonicalizer,
+        destination_policy,
     });
 
-    spawn_background_workers(state.clone(), rx, bg_redis_max_concurrency);
+    // 关闭信号：调度器、溢出回灌和后台作业共用
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let bg_workers = spawn_background_workers(
+        state.clone(),
+        rx,
+        bg_redis_max_concurrency,
+        shutdown_rx.clone(),
+    );
+
+    // 迁移旧版按短码分 key 存储的点击量
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            match state.redis_pool.get().await {
+                Ok(mut conn) => {
+                    if let Err(e) = Link::migrate_legacy_click_counts(&mut conn, 100).await {
+                        warn!("Failed to migrate legacy click counts: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("bg_redis: redis_pool.get() failed: {e}"),
+            }
+        }
+    });
+
+    // 定时任务调度，收到关闭信号后停止投递
+    let scheduler = spawn_scheduler(state.clone(), shutdown_rx.clone());
+    let spill_flusher = spawn_spill_flusher(state.clone(), shutdown_rx.clone());
+    let blocklist_reloader = spawn_blocklist_reloader(state.clone(), shutdown_rx);
 
     let public = Router::new()
-        .route("/s/{short_code}", get(handlers::redirect))
+        .route(
+            "/s/{short_code}",
+            get(handlers::redirect).post(handlers::unlock),
+        )
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             ip_rate_limiter,
@@ -77,7 +151,15 @@ async fn main() {
     // 保护路由
     let protected = Router::new()
         .route("/shorten", post(handlers::create))
+        .route("/shorten/batch", post(handlers::create_batch))
         .route("/links", get(handlers::list_links))
+        .route("/links/{id}", patch(handlers::update_link))
+        .route("/links/{id}/history", get(handlers::link_history))
+        .route("/links/{id}/reactivate", post(handlers::reactivate_link))
+        .route(
+            "/links/{id}/rollback/{revision}",
+            post(handlers::rollback_link),
+        )
         .route("/delete", post(handlers::delete_links))
         .route("/stats", get(handlers::get_link_stats))
         .layer(axum::middleware::from_fn_with_state(
@@ -100,9 +182,28 @@ async fn main() {
     // 启动服务
     let listener = TcpListener::bind(addr).await.unwrap();
     let shutdown_signal = async {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
+        let ctrl_c = async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("failed to install Ctrl+C handler");
+        };
+
+        // Docker 停止容器时发送的是 SIGTERM
+        #[cfg(unix)]
+        let terminate = async {
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("failed to install SIGTERM handler")
+                .recv()
+                .await;
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => {}
+            _ = terminate => {}
+        }
+        info!("shutdown signal received");
     };
     let make_svc = app.into_make_service_with_connect_info::<SocketAddr>();
 
@@ -110,4 +211,19 @@ async fn main() {
         .with_graceful_shutdown(shutdown_signal)
         .await
         .unwrap();
+
+    // HTTP 已停止，通知后台任务退出：先停止调度，再排空后台作业并做最后一次同步
+    let _ = shutdown_tx.send(true);
+    if let Err(e) = scheduler.await {
+        warn!("scheduler join error: {e}");
+    }
+    if let Err(e) = spill_flusher.await {
+        warn!("spill flusher join error: {e}");
+    }
+    if let Err(e) = blocklist_reloader.await {
+        warn!("blocklist reloader join error: {e}");
+    }
+    if let Err(e) = bg_workers.await {
+        warn!("background workers join error: {e}");
+    }
 }
@@ -2,20 +2,70 @@ use axum::http::StatusCode;
 use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 use deadpool_redis::Connection;
-use redis::AsyncCommands;
+use redis::{
+    AsyncCommands, Script,
+    streams::{
+        StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamPendingCountReply,
+        StreamRangeReply, StreamReadOptions, StreamReadReply,
+    },
+};
 use serde::{Deserialize, Serialize};
 use sqlx::{
     MySqlPool, QueryBuilder, Transaction,
     mysql::{MySql, MySqlDatabaseError, MySqlQueryResult},
     prelude::FromRow,
 };
-use std::collections::HashMap;
-use tracing::warn;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+use tracing::{info, warn};
 
 use crate::handlers::LinkQuery;
-
-#[derive(Debug, Default)]
+use crate::services::code_policy::skeleton;
+use crate::services::job_lease::Fence;
+use crate::services::url_normalize::url_hash;
+
+/// 待同步点击增量（哈希：短码 -> 增量）
+const CLICK_COUNTS_KEY: &str = "shortlink_clicks";
+/// 有待同步点击增量的短码集合
+const CLICK_DIRTY_KEY: &str = "shortlink_clicks_dirty";
+/// 待同步的最近点击时间（哈希：短码 -> Unix 秒），与点击增量一起同步
+const LAST_CLICK_KEY: &str = "shortlink_last_click";
+/// 已过期的短链：到达过期时间，或设置了闲置天数且最近点击（从未点击则为创建时间）已超过闲置天数
+const EXPIRED_CONDITION: &str = "((expire_at IS NOT NULL AND expire_at < NOW()) \
+     OR (inactivity_days IS NOT NULL \
+     AND COALESCE(last_click_at, created_at) < NOW() - INTERVAL inactivity_days DAY))";
+/// 已从待同步哈希取出、尚未确认写入 MySQL 的点击增量（哈希：短码 -> 增量）
+const CLICK_INFLIGHT_KEY: &str = "shortlink_clicks_inflight";
+/// 与暂存增量对应的最近点击时间（哈希：短码 -> Unix 秒）
+const LAST_CLICK_INFLIGHT_KEY: &str = "shortlink_last_click_inflight";
+/// 暂存批次号，提交 MySQL 后与暂存哈希一起删除
+const CLICK_INFLIGHT_BATCH_KEY: &str = "shortlink_clicks_inflight_batch";
+/// 点击量同步代数：每批提交 MySQL 后删除暂存时加一，用于判断读取的已同步点击量是否过时
+const CLICK_SYNC_EPOCH_KEY: &str = "shortlink_clicks_sync_epoch";
+/// 初始化限次短链剩余次数时，同步代数变化导致重读已同步点击量的最大次数
+const CLICK_BUDGET_SEED_RETRIES: usize = 3;
+/// 点击量同步在 `sync_checkpoints` 中的记录名
+const CLICK_SYNC_CHECKPOINT: &str = "click_counts";
+/// 过期短链清理进度（最后处理的 links.id）
+const PURGE_CURSOR_KEY: &str = "purge_cursor:expired_links";
+/// 短链已删除、访问日志和 Redis 残留尚待清理的短码
+const PURGE_PENDING_KEY: &str = "purge_pending:expired_links";
+/// 访问日志 Stream
+const VISIT_LOG_STREAM: &str = "visit_log";
+/// 访问日志同步消费组
+const VISIT_LOG_GROUP: &str = "visit_log_sync";
+/// 访问日志死信 Stream
+const VISIT_LOG_DEAD_STREAM: &str = "visit_log_dead";
+/// 限次短链剩余次数前缀（字符串：短码 -> 剩余次数）
+const CLICK_BUDGET_PREFIX: &str = "click_budget:";
+/// 返回 JSON 时的时间格式
+pub const VIEW_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug)]
 struct VisitLog {
+    stream_id: String,
     short_code: String,
     long_url: String,
     ip: String,
@@ -24,14 +74,37 @@ struct VisitLog {
     visit_time: String,
 }
 
+impl From<&StreamId> for VisitLog {
+    fn from(entry: &StreamId) -> Self {
+        let field = |name: &str| entry.get::<String>(name).unwrap_or_default();
+        VisitLog {
+            stream_id: entry.id.clone(),
+            short_code: field("short_code"),
+            long_url: field("long_url"),
+            ip: field("ip"),
+            user_agent: field("user_agent"),
+            referer: field("referer"),
+            visit_time: field("visit_time"),
+        }
+    }
+}
+
 #[derive(FromRow, Debug, Serialize, Deserialize)]
 pub struct LinkDto {
     pub id: u64,
     pub user_id: u64,
     pub short_code: String,
     pub long_url: String,
+    pub canonical_url: String,
     pub click_count: u64,
+    pub max_clicks: Option<u32>,
     pub expire_at: Option<NaiveDateTime>,
+    pub activate_at: Option<NaiveDateTime>,
+    pub inactivity_days: Option<u16>,
+    pub last_click_at: Option<NaiveDateTime>,
+    pub expired_landing_url: Option<String>,
+    /// 是否已过期（由查询计算，0 / 1）
+    pub expired: i64,
     pub created_at: NaiveDateTime,
 }
 
@@ -42,48 +115,180 @@ pub struct LinkView {
     pub user_id: u64,
     pub short_code: String,
     pub long_url: String,
+    /// 目标地址的规范形式
+    pub canonical_url: String,
     pub click_count: u64,
+    /// 允许的跳转次数，`None` 为不限
+    pub max_clicks: Option<u32>,
     pub expire_at: Option<String>,
+    /// 生效时间，`None` 为创建即生效
+    pub activate_at: Option<String>,
+    /// 闲置天数，超过该天数没有点击即过期，`None` 为不按闲置过期
+    pub inactivity_days: Option<u16>,
+    /// 最近点击时间（按点击量同步周期更新）
+    pub last_click_at: Option<String>,
+    /// 过期后宽限期内访问时引导到的落地页
+    pub expired_landing_url: Option<String>,
+    /// 是否已过期（宽限期内，可重新激活）
+    pub expired: bool,
+    pub created_at: String,
+}
+
+/// 待插入的一条短链
+pub struct LinkInsert<'a> {
+    pub long_url: &'a str,
+    /// 目标地址的规范形式
+    pub canonical_url: &'a str,
+    /// `None` 为永久有效
+    pub expire_at: Option<DateTime<Utc>>,
+    /// `None` 为创建即生效
+    pub activate_at: Option<DateTime<Utc>>,
+    /// 闲置天数，`None` 为不按闲置过期
+    pub inactivity_days: Option<u16>,
+    /// `None` 为不限跳转次数
+    pub max_clicks: Option<u32>,
+    /// 访问密码的 argon2 哈希，`None` 为不需要密码
+    pub password_hash: Option<&'a str>,
+    /// 过期落地页，`None` 为不跳转
+    pub expired_landing_url: Option<&'a str>,
+}
+
+/// 跳转时从 MySQL 读取的短链信息
+pub struct RedirectTarget {
+    pub long_url: String,
+    pub expire_at: Option<NaiveDateTime>,
+    pub activate_at: Option<NaiveDateTime>,
+    pub inactivity_days: Option<u16>,
+    /// 已同步到 MySQL 的最近点击时间
+    pub last_click_at: Option<NaiveDateTime>,
+    pub max_clicks: Option<u32>,
+    pub exhausted_at: Option<NaiveDateTime>,
+    /// 已同步到 MySQL 的点击量
+    pub click_count: u64,
+    /// 访问密码的 argon2 哈希
+    pub password_hash: Option<String>,
+    pub expired_landing_url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// 目标地址修改记录
+#[derive(FromRow)]
+struct LinkRevisionDto {
+    revision: u32,
+    user_id: u64,
+    action: String,
+    old_long_url: String,
+    new_long_url: String,
+    created_at: NaiveDateTime,
+}
+
+/// 目标地址修改记录（返回 JSON）
+#[derive(Serialize, Deserialize)]
+pub struct LinkRevisionView {
+    pub revision: u32,
+    pub user_id: u64,
+    pub action: String,
+    pub old_long_url: String,
+    pub new_long_url: String,
     pub created_at: String,
 }
 
+/// 是否已到生效时间，`None` 为创建即生效
+pub fn is_activated(activate_at: Option<DateTime<Utc>>) -> bool {
+    activate_at.is_none_or(|t| t <= Utc::now())
+}
+
 pub struct Link;
 
 impl Link {
-    /// 插入长 URL
+    /// 插入长 URL 及其规范形式（同时写入规范形式的哈希）
     pub async fn insert_long_url(
         tx: &mut Transaction<'_, MySql>,
-        long_url: &str,
-        expire_at: DateTime<Utc>,
+        row: &LinkInsert<'_>,
         user_id: u64,
     ) -> Result<MySqlQueryResult, (StatusCode, String)> {
-        let insert_sql =
-            sqlx::query(r#"INSERT INTO links (long_url, expire_at, user_id) VALUES (?, ?, ?)"#)
-                .bind(long_url)
-                .bind(expire_at)
-                .bind(user_id)
-                .execute(tx.as_mut())
-                .await
-                .map_err(|e| {
-                    warn!("insert_long_url: DB insert error: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("DB insert error: {}", e),
-                    )
-                })?;
+        let insert_sql = sqlx::query(
+            r#"INSERT INTO links
+                (long_url, canonical_url, url_hash, expire_at, activate_at, inactivity_days,
+                max_clicks, password_hash, expired_landing_url, user_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(row.long_url)
+        .bind(row.canonical_url)
+        .bind(url_hash(row.canonical_url))
+        .bind(row.expire_at)
+        .bind(row.activate_at)
+        .bind(row.inactivity_days)
+        .bind(row.max_clicks)
+        .bind(row.password_hash)
+        .bind(row.expired_landing_url)
+        .bind(user_id)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| {
+            warn!("insert_long_url: DB insert error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB insert error: {}", e),
+            )
+        })?;
 
         Ok(insert_sql)
     }
 
-    /// 更新短码
+    /// 查找用户已有的、目标地址相同、过期时间相同、已生效未过期、不限次数、不按闲置过期且无密码的短链，
+    /// 返回短码和过期时间
+    ///
+    /// 先按规范形式的哈希走索引，再要求原始地址 `long_url` 完全相同，避免去掉的跟踪参数（如 `utm_*`）
+    /// 不同的请求拿到同一条短链；`expire_at` 为 `None` 时只匹配永久短链，否则按秒匹配相同的过期时间。
+    pub async fn find_reusable_link(
+        mysql_pool: &MySqlPool,
+        user_id: u64,
+        long_url: &str,
+        canonical_url: &str,
+        expire_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<(String, Option<NaiveDateTime>)>, (StatusCode, String)> {
+        let row: Option<(String, Option<NaiveDateTime>)> = sqlx::query_as(
+            r#"SELECT short_code, expire_at FROM links
+            WHERE user_id = ? AND url_hash = ? AND canonical_url = ? AND long_url = ?
+                AND expire_at <=> CAST(? AS DATETIME) AND short_code IS NOT NULL
+                AND max_clicks IS NULL AND password_hash IS NULL AND inactivity_days IS NULL
+                AND (expire_at IS NULL OR expire_at > NOW())
+                AND (activate_at IS NULL OR activate_at <= NOW())
+            ORDER BY id DESC LIMIT 1"#,
+        )
+        .bind(user_id)
+        .bind(url_hash(canonical_url))
+        .bind(canonical_url)
+        .bind(long_url)
+        .bind(expire_at)
+        .fetch_optional(mysql_pool)
+        .await
+        .map_err(|e| {
+            warn!(
+                "find_reusable_link: DB select error: user_id={}, err={}",
+                user_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB select error: {}", e),
+            )
+        })?;
+
+        Ok(row)
+    }
+
+    /// 更新短码（同时写入形近骨架）
     pub async fn update_short_code(
         tx: &mut Transaction<'_, MySql>,
         id: u64,
         short_code: &str,
     ) -> Result<(), (StatusCode, String)> {
+        let code_skeleton = skeleton(short_code);
         sqlx::query!(
-            r#"UPDATE links SET short_code = ? WHERE id = ?"#,
+            r#"UPDATE links SET short_code = ?, code_skeleton = ? WHERE id = ?"#,
             short_code,
+            code_skeleton,
             id,
         )
         .execute(tx.as_mut())
@@ -107,13 +312,269 @@ impl Link {
         Ok(())
     }
 
+    /// 批量插入长 URL（单条多行 INSERT），按插入顺序返回 id
+    ///
+    /// 同一条语句插入的行 id 递增，但在 `innodb_autoinc_lock_mode = 2` 下不保证连续，
+    /// 因此插入后按 `id >= LAST_INSERT_ID()` 取回本事务内短码仍为空的行，并校验条数。
+    pub async fn insert_long_urls(
+        tx: &mut Transaction<'_, MySql>,
+        rows: &[LinkInsert<'_>],
+        user_id: u64,
+    ) -> Result<Vec<u64>, (StatusCode, String)> {
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new(
+            "INSERT INTO links \
+             (long_url, canonical_url, url_hash, expire_at, activate_at, inactivity_days, \
+             max_clicks, password_hash, expired_landing_url, user_id) ",
+        );
+        qb.push_values(rows, |mut b, row| {
+            b.push_bind(row.long_url)
+                .push_bind(row.canonical_url)
+                .push_bind(url_hash(row.canonical_url))
+                .push_bind(row.expire_at)
+                .push_bind(row.activate_at)
+                .push_bind(row.inactivity_days)
+                .push_bind(row.max_clicks)
+                .push_bind(row.password_hash)
+                .push_bind(row.expired_landing_url)
+                .push_bind(user_id);
+        });
+        let first_id = qb
+            .build()
+            .execute(tx.as_mut())
+            .await
+            .map_err(|e| {
+                warn!("insert_long_urls: DB insert error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB insert error: {}", e),
+                )
+            })?
+            .last_insert_id();
+
+        let ids: Vec<(u64,)> = sqlx::query_as(
+            r#"SELECT id FROM links
+            WHERE id >= ? AND user_id = ? AND short_code IS NULL
+            ORDER BY id LIMIT ?"#,
+        )
+        .bind(first_id)
+        .bind(user_id)
+        .bind(rows.len() as u64)
+        .fetch_all(tx.as_mut())
+        .await
+        .map_err(|e| {
+            warn!("insert_long_urls: DB select error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB select error: {}", e),
+            )
+        })?;
+
+        if ids.len() != rows.len() {
+            warn!(
+                "insert_long_urls: 插入行数不符: expected={}, got={}",
+                rows.len(),
+                ids.len()
+            );
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Batch insert id mismatch".into(),
+            ));
+        }
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// 查询已被占用的短码
+    pub async fn find_existing_codes(
+        tx: &mut Transaction<'_, MySql>,
+        codes: &[String],
+    ) -> Result<HashSet<String>, (StatusCode, String)> {
+        if codes.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let mut qb: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT short_code FROM links WHERE short_code IN (");
+        let mut sep = qb.separated(", ");
+        for code in codes {
+            sep.push_bind(code);
+        }
+        qb.push(")");
+        let rows: Vec<(String,)> =
+            qb.build_query_as()
+                .fetch_all(tx.as_mut())
+                .await
+                .map_err(|e| {
+                    warn!("find_existing_codes: DB select error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB select error: {}", e),
+                    )
+                })?;
+
+        Ok(rows.into_iter().map(|(code,)| code).collect())
+    }
+
+    /// 按形近骨架查询已有短码，返回 骨架 -> 已有短码
+    pub async fn find_similar_codes(
+        tx: &mut Transaction<'_, MySql>,
+        skeletons: &[String],
+    ) -> Result<HashMap<String, String>, (StatusCode, String)> {
+        if skeletons.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT code_skeleton, short_code FROM links WHERE code_skeleton IN (",
+        );
+        let mut sep = qb.separated(", ");
+        for sk in skeletons {
+            sep.push_bind(sk);
+        }
+        qb.push(")");
+        let rows: Vec<(String, String)> = qb
+            .build_query_as()
+            .fetch_all(tx.as_mut())
+            .await
+            .map_err(|e| {
+                warn!("find_similar_codes: DB select error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// 批量写入短码（单条 `CASE` UPDATE）；违反唯一约束时返回 CONFLICT
+    pub async fn update_short_codes(
+        tx: &mut Transaction<'_, MySql>,
+        codes: &[(u64, String)],
+    ) -> Result<(), (StatusCode, String)> {
+        if codes.is_empty() {
+            return Ok(());
+        }
+
+        let mut qb: QueryBuilder<MySql> =
+            QueryBuilder::new("UPDATE links SET short_code = CASE id");
+        for (id, code) in codes {
+            qb.push(" WHEN ")
+                .push_bind(id)
+                .push(" THEN ")
+                .push_bind(code);
+        }
+        qb.push(" END, code_skeleton = CASE id");
+        for (id, code) in codes {
+            qb.push(" WHEN ")
+                .push_bind(id)
+                .push(" THEN ")
+                .push_bind(skeleton(code));
+        }
+        qb.push(" END WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        for (id, _) in codes {
+            sep.push_bind(id);
+        }
+        qb.push(")");
+
+        qb.build().execute(tx.as_mut()).await.map_err(|e| {
+            warn!("update_short_codes: DB update error: {}", e);
+            let duplicate = e
+                .as_database_error()
+                .and_then(|db_err| db_err.try_downcast_ref::<MySqlDatabaseError>())
+                .is_some_and(|mysql_err| mysql_err.number() == 1062);
+            if duplicate {
+                return (StatusCode::CONFLICT, "Short code already exists".into());
+            }
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB update error: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// 删除尚未分配短码的短链（批量创建中写入短码失败的条目）
+    pub async fn delete_unassigned_links(
+        tx: &mut Transaction<'_, MySql>,
+        ids: &[u64],
+    ) -> Result<(), (StatusCode, String)> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new("DELETE FROM links WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        for id in ids {
+            sep.push_bind(id);
+        }
+        qb.push(") AND short_code IS NULL");
+        qb.build().execute(tx.as_mut()).await.map_err(|e| {
+            warn!("delete_unassigned_links: DB Delete error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Delete error: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// 批量写入跳转缓存并重置点击增量（单个 pipeline）
+    ///
+    /// `links` 为 `(短码, 长 URL, 缓存秒数)`，缓存秒数为 `None` 时只重置点击增量（限次短链不缓存）
+    pub async fn cache_shortlinks(
+        conn: &mut Connection,
+        links: &[(String, String, Option<i64>)],
+    ) -> Result<(), (StatusCode, String)> {
+        if links.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for (short_code, long_url, ttl) in links {
+            if let Some(ttl) = ttl {
+                pipe.set_ex(format!("shortlink:{}", short_code), long_url, *ttl as u64)
+                    .ignore();
+            }
+            pipe.hdel(CLICK_COUNTS_KEY, short_code)
+                .ignore()
+                .hdel(LAST_CLICK_KEY, short_code)
+                .ignore()
+                .hdel(CLICK_INFLIGHT_KEY, short_code)
+                .ignore()
+                .hdel(LAST_CLICK_INFLIGHT_KEY, short_code)
+                .ignore()
+                .srem(CLICK_DIRTY_KEY, short_code)
+                .ignore();
+        }
+        let _: () = pipe.query_async(conn).await.map_err(|e| {
+            warn!("cache_shortlinks: Redis pipeline error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Redis pipeline error: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+
     /// 设置短码
+    ///
+    /// 尚未到生效时间 `activate_at` 的短链不缓存，否则缓存命中会绕过生效时间检查。
     pub async fn set_shortlink(
         conn: &mut Connection,
         short_code: &str,
         long_url: &str,
         ttl: i64,
+        activate_at: Option<DateTime<Utc>>,
     ) -> Result<(), (StatusCode, String)> {
+        if !is_activated(activate_at) {
+            return Ok(());
+        }
+
         // 设置短链映射
         let url_key = format!("shortlink:{}", short_code);
         let _: () = conn
@@ -130,40 +591,56 @@ impl Link {
         Ok(())
     }
 
-    /// 设置短码点击量
+    /// 重置短码点击量（清掉可能残留的未同步增量，包括暂存中的同步批次）
     pub async fn set_click_count(
         conn: &mut Connection,
         short_code: &str,
-        click_ttl: i64,
     ) -> Result<(), (StatusCode, String)> {
-        let click_key = format!("shortlink_click:{}", short_code);
-        let _: () = redis::cmd("SET")
-            .arg(&click_key)
-            .arg(0)
-            .arg("NX")
-            .arg("EX")
-            .arg(click_ttl)
-            .query_async(conn)
-            .await
-            .map_err(|e| {
-                warn!("set_click_count: Redis SET NX EX error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Redis SET NX EX error: {}", e),
-                )
-            })?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hdel(CLICK_COUNTS_KEY, short_code)
+            .ignore()
+            .hdel(LAST_CLICK_KEY, short_code)
+            .ignore()
+            .hdel(CLICK_INFLIGHT_KEY, short_code)
+            .ignore()
+            .hdel(LAST_CLICK_INFLIGHT_KEY, short_code)
+            .ignore()
+            .srem(CLICK_DIRTY_KEY, short_code)
+            .ignore();
+        let _: () = pipe.query_async(conn).await.map_err(|e| {
+            warn!("set_click_count: Redis HDEL/SREM error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Redis HDEL/SREM error: {}", e),
+            )
+        })?;
 
         Ok(())
     }
 
-    /// 点击次数+1
-    pub async fn in_click_count(conn: &mut Connection, short_code: &str) {
-        let key = format!("shortlink_click:{}", short_code);
-        let result: redis::RedisResult<i64> = conn.incr(&key, 1).await;
+    /// 点击次数+1、记录最近点击时间，并把短码标记为待同步
+    pub async fn in_click_count(
+        conn: &mut Connection,
+        short_code: &str,
+    ) -> Result<(), (StatusCode, String)> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hincr(CLICK_COUNTS_KEY, short_code, 1)
+            .ignore()
+            .hset(LAST_CLICK_KEY, short_code, Utc::now().timestamp())
+            .ignore()
+            .sadd(CLICK_DIRTY_KEY, short_code)
+            .ignore();
+        let _: () = pipe.query_async(conn).await.map_err(|e| {
+            warn!("Redis HINCRBY error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Redis HINCRBY error: {}", e),
+            )
+        })?;
 
-        if let Err(e) = result {
-            warn!("Redis INCR error: {}", e);
-        }
+        Ok(())
     }
 
     /// 记录访问
@@ -174,11 +651,11 @@ impl Link {
         ip: &str,
         user_agent: &str,
         referer: &str,
-    ) {
+    ) -> Result<(), (StatusCode, String)> {
         let now = Utc::now().to_rfc3339();
-        let result: redis::RedisResult<String> = conn
+        let _: String = conn
             .xadd(
-                "visit_log",
+                VISIT_LOG_STREAM,
                 "*",
                 &[
                     ("short_code", short_code),
@@ -189,11 +666,16 @@ impl Link {
                     ("visit_time", &now),
                 ],
             )
-            .await;
+            .await
+            .map_err(|e| {
+                warn!("log_visit_to_stream: Redis xadd error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis xadd error: {}", e),
+                )
+            })?;
 
-        if let Err(e) = result {
-            warn!("log_visit_to_stream: Redis xadd error: {}", e);
-        }
+        Ok(())
     }
 
     /// 从 Redis 获取长 URL
@@ -218,9 +700,11 @@ impl Link {
     pub async fn get_logn_url_from_mysql(
         mysql_pool: &MySqlPool,
         short_code: &str,
-    ) -> Result<(String, Option<NaiveDateTime>), (StatusCode, String)> {
+    ) -> Result<RedirectTarget, (StatusCode, String)> {
         let row = sqlx::query!(
-            r#"SELECT long_url, expire_at FROM links WHERE short_code = ?"#,
+            r#"SELECT long_url, expire_at, activate_at, inactivity_days, last_click_at, max_clicks,
+                exhausted_at, click_count, password_hash, expired_landing_url, created_at
+            FROM links WHERE short_code = ?"#,
             short_code,
         )
         .fetch_optional(mysql_pool)
@@ -234,7 +718,19 @@ impl Link {
         })?;
 
         match row {
-            Some(row) => Ok((row.long_url, row.expire_at)),
+            Some(row) => Ok(RedirectTarget {
+                long_url: row.long_url,
+                expire_at: row.expire_at,
+                activate_at: row.activate_at,
+                inactivity_days: row.inactivity_days,
+                last_click_at: row.last_click_at,
+                max_clicks: row.max_clicks,
+                exhausted_at: row.exhausted_at,
+                click_count: row.click_count,
+                password_hash: row.password_hash,
+                expired_landing_url: row.expired_landing_url,
+                created_at: row.created_at,
+            }),
             None => {
                 warn!(
                     "get_logn_url_from_mysql: 短码不存在: short_code={}",
@@ -245,167 +741,840 @@ impl Link {
         }
     }
 
-    /// 同步点击量
-    pub async fn sync_click_counts(
-        mysql_pool: &MySqlPool,
+    /// 读取尚未同步到 MySQL 的最近点击时间（含同步中暂存的）
+    pub async fn get_pending_last_click(
         conn: &mut Connection,
-        batch: usize,
-    ) -> Result<(), (StatusCode, String)> {
-        let mut cursor: u64 = 0;
+        short_code: &str,
+    ) -> Result<Option<DateTime<Utc>>, (StatusCode, String)> {
+        let (pending, inflight): (Option<i64>, Option<i64>) = redis::pipe()
+            .hget(LAST_CLICK_KEY, short_code)
+            .hget(LAST_CLICK_INFLIGHT_KEY, short_code)
+            .query_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("get_pending_last_click: Redis HGET error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis HGET error: {}", e),
+                )
+            })?;
 
-        loop {
-            // 扫描 Redis 中的短码(100 个)
-            let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-                .arg(cursor)
-                .arg("MATCH")
-                .arg("shortlink_click:*")
-                .arg("COUNT")
-                .arg(batch)
-                .query_async(conn)
+        Ok(pending
+            .max(inflight)
+            .filter(|ts| *ts > 0)
+            .and_then(|ts| DateTime::from_timestamp(ts, 0)))
+    }
+
+    /// 读取点击量同步代数，需在读取 MySQL 中已同步的点击量之前调用，见 [`Self::consume_click_budget`]
+    pub async fn click_sync_epoch(conn: &mut Connection) -> Result<i64, (StatusCode, String)> {
+        let epoch: Option<i64> = conn.get(CLICK_SYNC_EPOCH_KEY).await.map_err(|e| {
+            warn!("click_sync_epoch: Redis GET error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Redis GET error: {}", e),
+            )
+        })?;
+
+        Ok(epoch.unwrap_or(0))
+    }
+
+    /// 扣减限次短链的剩余次数，返回扣减后的剩余次数；次数已用完返回 `None`
+    ///
+    /// 剩余次数不存在（首次访问或被淘汰）时，按 `max_clicks` 减去 MySQL 中已同步的 `synced_clicks`
+    /// 和 Redis 中待同步、同步中的点击量，在脚本内用 `SET NX` 初始化；`ttl` 为剩余次数的有效期（秒），
+    /// `None` 表示不过期。初始化和扣减在同一个脚本内完成，多副本并发访问也不会超发。
+    ///
+    /// `synced_clicks` 须在 `sync_epoch`（[`Self::click_sync_epoch`]）之后读取：同步批次提交后、
+    /// 删除暂存时同步代数加一，脚本发现代数已变化说明读到的已同步点击量可能不含刚删除的暂存，
+    /// 不初始化，改为重新读取代数和点击量后重试，最多 `CLICK_BUDGET_SEED_RETRIES` 次。
+    /// 在批次提交后、删除暂存前初始化时暂存会被重复扣除，只会少给，不会超发。
+    ///
+    /// 扣减成功时在同一个脚本内记入待同步点击量，不经过后台队列：否则仍在队列或溢出缓冲区中的点击
+    /// 不会计入重新初始化的剩余次数，剩余次数被淘汰后会超发。调用方推送访问日志时不应再次计数。
+    pub async fn consume_click_budget(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        short_code: &str,
+        max_clicks: u32,
+        synced_clicks: u64,
+        sync_epoch: i64,
+        ttl: Option<i64>,
+    ) -> Result<Option<u64>, (StatusCode, String)> {
+        let consume = Script::new(
+            r#"
+            local remaining = redis.call('GET', KEYS[1])
+            if not remaining then
+                if (redis.call('GET', KEYS[6]) or '0') ~= ARGV[5] then
+                    return -2
+                end
+                remaining = tonumber(ARGV[2]) - tonumber(redis.call('HGET', KEYS[2], ARGV[1]) or '0')
+                    - tonumber(redis.call('HGET', KEYS[3], ARGV[1]) or '0')
+                if tonumber(ARGV[3]) > 0 then
+                    redis.call('SET', KEYS[1], remaining, 'NX', 'EX', ARGV[3])
+                else
+                    redis.call('SET', KEYS[1], remaining, 'NX')
+                end
+            end
+            if tonumber(remaining) <= 0 then
+                return -1
+            end
+            redis.call('HINCRBY', KEYS[2], ARGV[1], 1)
+            redis.call('HSET', KEYS[4], ARGV[1], ARGV[4])
+            redis.call('SADD', KEYS[5], ARGV[1])
+            return redis.call('DECR', KEYS[1])
+            "#,
+        );
+
+        let (mut synced_clicks, mut sync_epoch) = (synced_clicks, sync_epoch);
+        for _ in 0..=CLICK_BUDGET_SEED_RETRIES {
+            let remaining: i64 = consume
+                .key(format!("{}{}", CLICK_BUDGET_PREFIX, short_code))
+                .key(CLICK_COUNTS_KEY)
+                .key(CLICK_INFLIGHT_KEY)
+                .key(LAST_CLICK_KEY)
+                .key(CLICK_DIRTY_KEY)
+                .key(CLICK_SYNC_EPOCH_KEY)
+                .arg(short_code)
+                .arg(max_clicks as i64 - synced_clicks as i64)
+                .arg(ttl.unwrap_or(0))
+                .arg(Utc::now().timestamp())
+                .arg(sync_epoch)
+                .invoke_async(conn)
                 .await
                 .map_err(|e| {
-                    warn!("sync_click_counts: Redis scan error: {}", e);
+                    warn!(
+                        "consume_click_budget: Redis script error: short_code={}, err={}",
+                        short_code, e
+                    );
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Redis scan error: {}", e),
+                        format!("Redis script error: {}", e),
                     )
                 })?;
+            if remaining != -2 {
+                return Ok((remaining >= 0).then_some(remaining as u64));
+            }
 
-            // 遍历短码
-            for key in keys {
-                // 获取短码
-                if let Some(code) = key.strip_prefix("shortlink_click:") {
-                    // 获取短码点击量
-                    let click_count: Option<i64> = conn.get(&key).await.map_err(|e| {
-                        warn!("sync_click_counts: Redis get error: {} code={}", e, code);
+            // 读取已同步点击量之后有批次完成同步，先读代数再重读点击量
+            sync_epoch = Self::click_sync_epoch(conn).await?;
+            let row: Option<(u64,)> =
+                sqlx::query_as("SELECT click_count FROM links WHERE short_code = ?")
+                    .bind(short_code)
+                    .fetch_optional(mysql_pool)
+                    .await
+                    .map_err(|e| {
+                        warn!(
+                            "consume_click_budget: DB select error: short_code={}, err={}",
+                            short_code, e
+                        );
                         (
                             StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Redis get error: {}", e),
+                            format!("DB select error: {}", e),
                         )
                     })?;
-
-                    if let Some(click_count) = click_count {
-                        // 如果点击量大于 0 更新 MySQL
-                        if click_count > 0 {
-                            sqlx::query!(
-                                r#"UPDATE links SET click_count = click_count + ? WHERE short_code = ?"#,
-                                click_count,
-                                code,
-                            )
-                            .execute(mysql_pool)
-                            .await
-                            .map_err(|e| {
-                                warn!("sync_click_counts: DB update error: {} code={}", e, code);
-                                (StatusCode::INTERNAL_SERVER_ERROR, format!("DB update error: {}", e))
-                            })?;
-
-                            // 将 Redis 点击量重置为 0
-                            let _: () = conn.set(&key, 0_i64).await.map_err(|e| {
-                                warn!("sync_click_counts: Redis set error: {} code={}", e, code);
-                                (
-                                    StatusCode::INTERNAL_SERVER_ERROR,
-                                    format!("Redis set error: {}", e),
-                                )
-                            })?;
-                        }
-                    }
-                }
-            }
-
-            // 如果没有短码了，退出循环
-            if next_cursor == 0 {
-                break;
-            }
-            cursor = next_cursor;
+            let Some((clicks,)) = row else {
+                return Err((StatusCode::NOT_FOUND, "Short code not found".into()));
+            };
+            synced_clicks = clicks;
         }
 
-        Ok(())
+        warn!(
+            "consume_click_budget: 同步代数持续变化，无法初始化剩余次数: short_code={}",
+            short_code
+        );
+        Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Click budget is being synced, retry later".into(),
+        ))
     }
 
-    /// 同步访问日志
-    pub async fn sync_visit_logs(
+    /// 标记限次短链的次数已用完
+    pub async fn mark_exhausted(
         mysql_pool: &MySqlPool,
-        conn: &mut Connection,
-        batch: usize,
+        short_code: &str,
     ) -> Result<(), (StatusCode, String)> {
-        loop {
-            // 1. 从 Stream 读出一批记录（XRANGE visit_log - + COUNT batch）
-            //    返回值形如 Vec<(id, Vec<(field, value)>)>
-            let entries: Vec<(String, Vec<(String, String)>)> = redis::cmd("XRANGE")
-                .arg("visit_log")
-                .arg("-")
-                .arg("+")
-                .arg("COUNT")
-                .arg(batch)
-                .query_async(conn)
-                .await
-                .map_err(|e| {
-                    warn!("sync_visit_logs: Redis XRANGE error: {}", e);
-                    (
+        sqlx::query(
+            "UPDATE links SET exhausted_at = ? WHERE short_code = ? AND exhausted_at IS NULL",
+        )
+        .bind(Utc::now())
+        .bind(short_code)
+        .execute(mysql_pool)
+        .await
+        .map_err(|e| {
+            warn!(
+                "mark_exhausted: DB update error: short_code={}, err={}",
+                short_code, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB update error: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// 同步点击量和最近点击时间
+    ///
+    /// 每轮从待同步集合中原子弹出最多 `batch` 个短码，把增量和最近点击时间移入暂存哈希
+    /// （`shortlink_clicks_inflight` / `shortlink_last_click_inflight`）并分配批次号，再用一条 UPDATE
+    /// 批量累加点击量、更新 `last_click_at`；同一事务内校验租约围栏令牌 `fence`，并把批次号写入
+    /// `sync_checkpoints`，提交后才删除暂存。
+    ///
+    /// 取出后作业被取消、进程退出、MySQL 失败或租约已被接管时暂存保留，下次同步先重做这一批；
+    /// 已提交但来不及删除暂存的批次按检查点中的批次号识别，不会重复累加。
+    ///
+    /// 到达截止时间 `deadline` 后不再取新批次（服务关闭时使用），剩余的留给下次同步。
+    pub async fn sync_click_counts(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        batch: usize,
+        fence: Fence,
+        deadline: Option<Instant>,
+    ) -> Result<(), (StatusCode, String)> {
+        // 有未完成的批次时原样取回；否则弹出一批待同步短码，把增量和最近点击时间移入暂存并记下批次号。
+        // 返回 {短码数, 批次号, [code, delta, ts, code, delta, ts, ...]}
+        let drain = Script::new(
+            r#"
+            local batch_id = redis.call('GET', KEYS[6])
+            if batch_id and redis.call('EXISTS', KEYS[4]) == 0 then
+                redis.call('DEL', KEYS[5], KEYS[6])
+                batch_id = false
+            end
+            local popped = 0
+            if not batch_id then
+                local codes = redis.call('SPOP', KEYS[2], ARGV[1])
+                popped = #codes
+                local moved = 0
+                for _, code in ipairs(codes) do
+                    local v = tonumber(redis.call('HGET', KEYS[1], code) or '0')
+                    local t = tonumber(redis.call('HGET', KEYS[3], code) or '0')
+                    redis.call('HDEL', KEYS[1], code)
+                    redis.call('HDEL', KEYS[3], code)
+                    if v > 0 or t > 0 then
+                        redis.call('HSET', KEYS[4], code, v)
+                        redis.call('HSET', KEYS[5], code, t)
+                        moved = moved + 1
+                    end
+                end
+                if moved == 0 then
+                    return {popped, '', {}}
+                end
+                batch_id = ARGV[2]
+                redis.call('SET', KEYS[6], batch_id)
+            end
+            local out = {}
+            local inflight = redis.call('HGETALL', KEYS[4])
+            for i = 1, #inflight, 2 do
+                table.insert(out, inflight[i])
+                table.insert(out, tonumber(inflight[i + 1]))
+                table.insert(out, tonumber(redis.call('HGET', KEYS[5], inflight[i]) or '0'))
+            end
+            return {math.max(popped, #out / 3), batch_id, out}
+        "#,
+        );
+        // 提交后删除暂存并递增同步代数，批次号不一致说明已被处理
+        let finish = Script::new(
+            r#"
+            if redis.call('GET', KEYS[3]) == ARGV[1] then
+                redis.call('DEL', KEYS[1], KEYS[2], KEYS[3])
+                redis.call('INCR', KEYS[4])
+            end
+            return 0
+        "#,
+        );
+
+        loop {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                warn!("sync_click_counts: 到达截止时间，剩余点击量留待下次同步");
+                break;
+            }
+
+            let (popped, batch_id, deltas): (usize, String, Vec<(String, i64, i64)>) = drain
+                .key(CLICK_COUNTS_KEY)
+                .key(CLICK_DIRTY_KEY)
+                .key(LAST_CLICK_KEY)
+                .key(CLICK_INFLIGHT_KEY)
+                .key(LAST_CLICK_INFLIGHT_KEY)
+                .key(CLICK_INFLIGHT_BATCH_KEY)
+                .arg(batch)
+                .arg(format!("{:016x}", rand::random::<u64>()))
+                .invoke_async(conn)
+                .await
+                .map_err(|e| {
+                    warn!("sync_click_counts: Redis drain error: {}", e);
+                    (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Redis XRANGE error: {}", e),
+                        format!("Redis drain error: {}", e),
                     )
                 })?;
 
-            // 若没有更多日志则结束
-            if entries.is_empty() {
+            // 没有待同步的短码了，退出循环
+            if popped == 0 {
                 break;
             }
+            if deltas.is_empty() {
+                continue;
+            }
 
-            for (entry_id, kvs) in entries {
-                // 2. 把字段映射到变量
-                let mut visit_log = VisitLog::default();
-
-                for (field, value) in kvs {
-                    match field.as_str() {
-                        "short_code" => visit_log.short_code = value,
-                        "long_url" => visit_log.long_url = value,
-                        "ip" => visit_log.ip = value,
-                        "user_agent" => visit_log.user_agent = value,
-                        "referer" => visit_log.referer = value,
-                        "visit_time" => visit_log.visit_time = value,
-                        _ => {}
-                    }
+            let mut qb: QueryBuilder<MySql> =
+                QueryBuilder::new("UPDATE links SET click_count = click_count + CASE short_code ");
+            for (code, delta, _) in &deltas {
+                qb.push(" WHEN ")
+                    .push_bind(code)
+                    .push(" THEN ")
+                    .push_bind(delta);
+            }
+            qb.push(" ELSE 0 END");
+            let last_clicks: Vec<(&String, DateTime<Utc>)> = deltas
+                .iter()
+                .filter(|(_, _, ts)| *ts > 0)
+                .filter_map(|(code, _, ts)| DateTime::from_timestamp(*ts, 0).map(|t| (code, t)))
+                .collect();
+            if !last_clicks.is_empty() {
+                qb.push(", last_click_at = CASE short_code ");
+                for (code, t) in &last_clicks {
+                    qb.push(" WHEN ")
+                        .push_bind(*code)
+                        .push(" THEN GREATEST(COALESCE(last_click_at, ")
+                        .push_bind(*t)
+                        .push("), ")
+                        .push_bind(*t)
+                        .push(")");
                 }
+                qb.push(" ELSE last_click_at END");
+            }
+            qb.push(" WHERE short_code IN (");
+            let mut sep = qb.separated(", ");
+            for (code, _, _) in &deltas {
+                sep.push_bind(code);
+            }
+            qb.push(")");
 
-                // 3. 写入 MySQL
-                sqlx::query!(
-                    r#"INSERT INTO visit_logs
-                       (short_code, long_url, ip, user_agent, referer, visit_time)
-                       VALUES (?, ?, ?, ?, ?, ?)"#,
-                    visit_log.short_code,
-                    visit_log.long_url,
-                    visit_log.ip,
-                    visit_log.user_agent,
-                    visit_log.referer,
-                    visit_log.visit_time,
+            let mut tx = mysql_pool.begin().await.map_err(|e| {
+                warn!("sync_click_counts: DB Begin error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB Begin error: {}", e),
                 )
-                .execute(mysql_pool)
+            })?;
+            fence.check(&mut tx).await?;
+
+            let applied: Option<(String,)> =
+                sqlx::query_as("SELECT batch_id FROM sync_checkpoints WHERE name = ? FOR UPDATE")
+                    .bind(CLICK_SYNC_CHECKPOINT)
+                    .fetch_optional(tx.as_mut())
+                    .await
+                    .map_err(|e| {
+                        warn!("sync_click_counts: DB select checkpoint error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("DB select error: {}", e),
+                        )
+                    })?;
+            if applied.is_some_and(|(id,)| id == batch_id) {
+                info!(
+                    "sync_click_counts: 批次已提交，只清理暂存: batch_id={}",
+                    batch_id
+                );
+            } else {
+                qb.build().execute(tx.as_mut()).await.map_err(|e| {
+                    warn!(
+                        "sync_click_counts: DB update error: batch_id={}, err={}",
+                        batch_id, e
+                    );
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB update error: {}", e),
+                    )
+                })?;
+                sqlx::query(
+                    "INSERT INTO sync_checkpoints (name, batch_id) VALUES (?, ?) \
+                     ON DUPLICATE KEY UPDATE batch_id = VALUES(batch_id)",
+                )
+                .bind(CLICK_SYNC_CHECKPOINT)
+                .bind(&batch_id)
+                .execute(tx.as_mut())
                 .await
                 .map_err(|e| {
-                    warn!("sync_visit_logs: DB insert error: {}", e);
+                    warn!("sync_click_counts: DB update checkpoint error: {}", e);
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("DB insert error: {}", e),
+                        format!("DB update error: {}", e),
                     )
                 })?;
+            }
 
-                // 4. 删除已同步的 Stream 条目，避免重复同步
-                let _: () = redis::cmd("XDEL")
-                    .arg("visit_log")
-                    .arg(&entry_id)
-                    .query_async(conn)
-                    .await
-                    .map_err(|e| {
-                        warn!("sync_visit_logs: Redis XDEL error: {}", e);
+            tx.commit().await.map_err(|e| {
+                warn!("sync_click_counts: DB Commit error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB Commit error: {}", e),
+                )
+            })?;
+
+            let _: i64 = finish
+                .key(CLICK_INFLIGHT_KEY)
+                .key(LAST_CLICK_INFLIGHT_KEY)
+                .key(CLICK_INFLIGHT_BATCH_KEY)
+                .key(CLICK_SYNC_EPOCH_KEY)
+                .arg(&batch_id)
+                .invoke_async(conn)
+                .await
+                .map_err(|e| {
+                    warn!(
+                        "sync_click_counts: Redis finish error: batch_id={}, err={}",
+                        batch_id, e
+                    );
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis finish error: {}", e),
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// 把旧版按短码分 key 存储的点击量（`shortlink_click:{code}`）迁移到哈希计数器
+    ///
+    /// 只在启动时执行一次，迁移完成后旧 key 被删除。
+    pub async fn migrate_legacy_click_counts(
+        conn: &mut Connection,
+        batch: usize,
+    ) -> Result<(), (StatusCode, String)> {
+        let migrate = Script::new(
+            r#"
+            local v = tonumber(redis.call('GET', KEYS[1]) or '0')
+            redis.call('DEL', KEYS[1])
+            if v > 0 then
+                redis.call('HINCRBY', KEYS[2], ARGV[1], v)
+                redis.call('SADD', KEYS[3], ARGV[1])
+            end
+            return v
+        "#,
+        );
+
+        let mut cursor: u64 = 0;
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg("shortlink_click:*")
+                .arg("COUNT")
+                .arg(batch)
+                .query_async(conn)
+                .await
+                .map_err(|e| {
+                    warn!("migrate_legacy_click_counts: Redis scan error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis scan error: {}", e),
+                    )
+                })?;
+
+            for key in keys {
+                if let Some(code) = key.strip_prefix("shortlink_click:") {
+                    let _: i64 = migrate
+                        .key(&key)
+                        .key(CLICK_COUNTS_KEY)
+                        .key(CLICK_DIRTY_KEY)
+                        .arg(code)
+                        .invoke_async(conn)
+                        .await
+                        .map_err(|e| {
+                            warn!(
+                                "migrate_legacy_click_counts: Redis error: {} code={}",
+                                e, code
+                            );
+                            (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                format!("Redis error: {}", e),
+                            )
+                        })?;
+                }
+            }
+
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+
+        Ok(())
+    }
+
+    /// 确保访问日志 Stream 的消费组存在（Stream 不存在时一并创建）
+    async fn ensure_visit_log_group(conn: &mut Connection) -> Result<(), (StatusCode, String)> {
+        let result: redis::RedisResult<()> = conn
+            .xgroup_create_mkstream(VISIT_LOG_STREAM, VISIT_LOG_GROUP, "0")
+            .await;
+
+        match result {
+            Ok(()) => Ok(()),
+            // 消费组已存在
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            Err(e) => {
+                warn!("ensure_visit_log_group: Redis XGROUP CREATE error: {}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis XGROUP CREATE error: {}", e),
+                ))
+            }
+        }
+    }
+
+    /// 删除消费组中空闲超过 `idle_ms` 且没有待确认条目的其他消费者
+    ///
+    /// 每次重启都以新的实例 id 加入消费组，旧消费者的待确认条目被接管后不再有用，
+    /// 不清理的话 `XINFO CONSUMERS` 会无限增长。检查与删除在同一脚本内原子执行，
+    /// 不会误删刚领取了条目的消费者；返回删除的个数。
+    async fn prune_visit_log_consumers(
+        conn: &mut Connection,
+        consumer: &str,
+        idle_ms: u64,
+    ) -> Result<u64, (StatusCode, String)> {
+        let script = Script::new(
+            r#"
+            local removed = 0
+            for _, c in ipairs(redis.call('XINFO', 'CONSUMERS', KEYS[1], ARGV[1])) do
+                local info = {}
+                for i = 1, #c, 2 do
+                    info[c[i]] = c[i + 1]
+                end
+                if info['name'] ~= ARGV[2] and tonumber(info['pending']) == 0
+                    and tonumber(info['idle']) >= tonumber(ARGV[3]) then
+                    redis.call('XGROUP', 'DELCONSUMER', KEYS[1], ARGV[1], info['name'])
+                    removed = removed + 1
+                end
+            end
+            return removed
+        "#,
+        );
+
+        let removed: u64 = script
+            .key(VISIT_LOG_STREAM)
+            .arg(VISIT_LOG_GROUP)
+            .arg(consumer)
+            .arg(idle_ms)
+            .invoke_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("prune_visit_log_consumers: Redis error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis error: {}", e),
+                )
+            })?;
+        if removed > 0 {
+            info!(
+                "prune_visit_log_consumers: 已删除空闲消费者: removed={}",
+                removed
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// 多行插入访问日志，每条语句最多 `max_rows` 行
+    async fn insert_visit_logs(
+        tx: &mut Transaction<'_, MySql>,
+        logs: &[VisitLog],
+        max_rows: usize,
+    ) -> Result<(), sqlx::Error> {
+        for chunk in logs.chunks(max_rows.max(1)) {
+            let mut qb: QueryBuilder<MySql> = QueryBuilder::new(
+                "INSERT INTO visit_logs \
+                 (stream_id, short_code, long_url, ip, user_agent, referer, visit_time) ",
+            );
+            qb.push_values(chunk, |mut b, log| {
+                b.push_bind(&log.stream_id)
+                    .push_bind(&log.short_code)
+                    .push_bind(&log.long_url)
+                    .push_bind(&log.ip)
+                    .push_bind(&log.user_agent)
+                    .push_bind(&log.referer)
+                    .push_bind(&log.visit_time);
+            });
+            qb.push(" ON DUPLICATE KEY UPDATE stream_id = stream_id");
+            qb.build().execute(tx.as_mut()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 将一批 Stream 条目写入 MySQL，成功的条目 XACK 并 XDEL
+    ///
+    /// 整批在一个事务内按 `max_rows` 分段多行插入，提交后一次管道调用 XACK + XDEL。
+    /// 以 Stream 条目 ID 做幂等（`visit_logs.stream_id` 唯一），重复投递不会重复插入。
+    /// 批量插入被数据库拒绝时回退为逐条插入以隔离毒消息：单条数据错误只记录告警并
+    /// 保留在 PEL 中等待重试，超过投递上限后进入死信；连接类错误直接返回。
+    async fn write_visit_entries(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        entries: Vec<StreamId>,
+        max_rows: usize,
+    ) -> Result<usize, (StatusCode, String)> {
+        let logs: Vec<VisitLog> = entries.iter().map(VisitLog::from).collect();
+
+        let mut tx = mysql_pool.begin().await.map_err(|e| {
+            warn!("write_visit_entries: DB Begin error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Begin error: {}", e),
+            )
+        })?;
+
+        let done: Vec<String> = match Self::insert_visit_logs(&mut tx, &logs, max_rows).await {
+            Ok(()) => {
+                tx.commit().await.map_err(|e| {
+                    warn!("write_visit_entries: DB Commit error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB Commit error: {}", e),
+                    )
+                })?;
+                logs.into_iter().map(|log| log.stream_id).collect()
+            }
+            Err(sqlx::Error::Database(e)) => {
+                warn!(
+                    "write_visit_entries: batch insert rejected, fallback to row by row: {}",
+                    e
+                );
+                let _ = tx.rollback().await;
+                Self::write_visit_logs_one_by_one(mysql_pool, logs).await?
+            }
+            Err(e) => {
+                warn!("write_visit_entries: DB insert error: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB insert error: {}", e),
+                ));
+            }
+        };
+
+        if done.is_empty() {
+            return Ok(0);
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.xack(VISIT_LOG_STREAM, VISIT_LOG_GROUP, &done)
+            .ignore()
+            .xdel(VISIT_LOG_STREAM, &done)
+            .ignore();
+        let _: () = pipe.query_async(conn).await.map_err(|e| {
+            warn!("write_visit_entries: Redis XACK/XDEL error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Redis XACK/XDEL error: {}", e),
+            )
+        })?;
+
+        Ok(done.len())
+    }
+
+    /// 逐条插入访问日志，返回写入成功的 Stream 条目 ID
+    async fn write_visit_logs_one_by_one(
+        mysql_pool: &MySqlPool,
+        logs: Vec<VisitLog>,
+    ) -> Result<Vec<String>, (StatusCode, String)> {
+        let mut done = Vec::with_capacity(logs.len());
+
+        for log in logs {
+            let mut tx = mysql_pool.begin().await.map_err(|e| {
+                warn!("write_visit_logs_one_by_one: DB Begin error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB Begin error: {}", e),
+                )
+            })?;
+
+            match Self::insert_visit_logs(&mut tx, std::slice::from_ref(&log), 1).await {
+                Ok(()) => {
+                    tx.commit().await.map_err(|e| {
+                        warn!("write_visit_logs_one_by_one: DB Commit error: {}", e);
                         (
                             StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Redis XDEL error: {}", e),
+                            format!("DB Commit error: {}", e),
                         )
                     })?;
+                    done.push(log.stream_id);
+                }
+                Err(sqlx::Error::Database(e)) => {
+                    warn!(
+                        "write_visit_logs_one_by_one: DB insert rejected: id={}, err={}",
+                        log.stream_id, e
+                    );
+                }
+                Err(e) => {
+                    warn!("write_visit_logs_one_by_one: DB insert error: {}", e);
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB insert error: {}", e),
+                    ));
+                }
             }
         }
 
+        Ok(done)
+    }
+
+    /// 把投递次数超过上限的毒消息移入死信 Stream
+    async fn dead_letter_visit_logs(
+        conn: &mut Connection,
+        batch: usize,
+        claim_idle_ms: u64,
+        max_deliveries: usize,
+    ) -> Result<(), (StatusCode, String)> {
+        let pending: StreamPendingCountReply = redis::cmd("XPENDING")
+            .arg(VISIT_LOG_STREAM)
+            .arg(VISIT_LOG_GROUP)
+            .arg("IDLE")
+            .arg(claim_idle_ms)
+            .arg("-")
+            .arg("+")
+            .arg(batch)
+            .query_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("dead_letter_visit_logs: Redis XPENDING error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis XPENDING error: {}", e),
+                )
+            })?;
+
+        for p in pending
+            .ids
+            .into_iter()
+            .filter(|p| p.times_delivered >= max_deliveries)
+        {
+            let range: StreamRangeReply = conn
+                .xrange(VISIT_LOG_STREAM, &p.id, &p.id)
+                .await
+                .map_err(|e| {
+                    warn!("dead_letter_visit_logs: Redis XRANGE error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis XRANGE error: {}", e),
+                    )
+                })?;
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            // 条目可能已被删除，只需 ACK
+            if let Some(entry) = range.ids.first() {
+                let mut fields: Vec<(String, String)> = entry
+                    .map
+                    .keys()
+                    .map(|k| (k.clone(), entry.get::<String>(k).unwrap_or_default()))
+                    .collect();
+                fields.push(("origin_id".into(), p.id.clone()));
+                fields.push(("deliveries".into(), p.times_delivered.to_string()));
+                pipe.xadd(VISIT_LOG_DEAD_STREAM, "*", &fields).ignore();
+            }
+            pipe.xack(VISIT_LOG_STREAM, VISIT_LOG_GROUP, &[&p.id])
+                .ignore()
+                .xdel(VISIT_LOG_STREAM, &[&p.id])
+                .ignore();
+            let _: () = pipe.query_async(conn).await.map_err(|e| {
+                warn!("dead_letter_visit_logs: Redis pipeline error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis pipeline error: {}", e),
+                )
+            })?;
+
+            warn!(
+                "dead_letter_visit_logs: moved to {}: id={}, deliveries={}",
+                VISIT_LOG_DEAD_STREAM, p.id, p.times_delivered
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 同步访问日志
+    ///
+    /// 基于消费组 `visit_log_sync` 消费 `visit_log` Stream：
+    /// 1. 投递次数达到 `max_deliveries` 的毒消息移入死信 Stream `visit_log_dead`；
+    /// 2. XAUTOCLAIM 接管空闲超过 `claim_idle_ms` 的待确认条目（宕机副本或上次失败遗留）；
+    /// 3. 删除空闲超过 `claim_idle_ms` 且没有待确认条目的其他消费者（旧实例）；
+    /// 4. XREADGROUP 读取新条目直到读空。
+    ///
+    /// 每次从 Stream 读取 `batch` 条，按每条语句最多 `max_rows` 行批量写入。
+    pub async fn sync_visit_logs(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        consumer: &str,
+        batch: usize,
+        max_rows: usize,
+        claim_idle_ms: u64,
+        max_deliveries: usize,
+    ) -> Result<(), (StatusCode, String)> {
+        Self::ensure_visit_log_group(conn).await?;
+
+        Self::dead_letter_visit_logs(conn, batch, claim_idle_ms, max_deliveries).await?;
+
+        // 接管其他消费者遗留的待确认条目
+        let mut cursor = "0-0".to_string();
+        loop {
+            let reply: StreamAutoClaimReply = conn
+                .xautoclaim_options(
+                    VISIT_LOG_STREAM,
+                    VISIT_LOG_GROUP,
+                    consumer,
+                    claim_idle_ms,
+                    &cursor,
+                    StreamAutoClaimOptions::default().count(batch),
+                )
+                .await
+                .map_err(|e| {
+                    warn!("sync_visit_logs: Redis XAUTOCLAIM error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis XAUTOCLAIM error: {}", e),
+                    )
+                })?;
+
+            if !reply.claimed.is_empty() {
+                Self::write_visit_entries(mysql_pool, conn, reply.claimed, max_rows).await?;
+            }
+
+            if reply.next_stream_id == "0-0" {
+                break;
+            }
+            cursor = reply.next_stream_id;
+        }
+
+        // 遗留条目已接管，清理没有待确认条目的旧消费者
+        Self::prune_visit_log_consumers(conn, consumer, claim_idle_ms).await?;
+
+        // 读取新条目
+        let opts = StreamReadOptions::default()
+            .group(VISIT_LOG_GROUP, consumer)
+            .count(batch);
+        loop {
+            let reply: StreamReadReply = conn
+                .xread_options(&[VISIT_LOG_STREAM], &[">"], &opts)
+                .await
+                .map_err(|e| {
+                    warn!("sync_visit_logs: Redis XREADGROUP error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis XREADGROUP error: {}", e),
+                    )
+                })?;
+
+            let entries: Vec<StreamId> = reply.keys.into_iter().flat_map(|k| k.ids).collect();
+            // 若没有更多日志则结束
+            if entries.is_empty() {
+                break;
+            }
+
+            Self::write_visit_entries(mysql_pool, conn, entries, max_rows).await?;
+        }
+
         Ok(())
     }
 
@@ -420,9 +1589,22 @@ impl Link {
                 .push_bind(format!("%{}%", short_code));
         }
 
+        // 同时匹配原始地址和规范形式；关键字本身是完整 URL 时还按其规范形式前缀匹配
         if let Some(long_url) = filter.long_url.as_deref() {
-            qb.push(" AND long_url LIKE ")
-                .push_bind(format!("%{}%", long_url));
+            let pattern = format!("%{}%", long_url);
+            qb.push(" AND (long_url LIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR canonical_url LIKE ")
+                .push_bind(pattern);
+            if let Some(canonical_url) = filter.canonical_url.as_deref() {
+                let escaped = canonical_url
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                qb.push(" OR canonical_url LIKE ")
+                    .push_bind(format!("{}%", escaped));
+            }
+            qb.push(")");
         }
         if let Some(click_count) = filter.click_count {
             qb.push(" AND click_count = ").push_bind(click_count);
@@ -436,20 +1618,29 @@ impl Link {
             qb.push(" AND created_at <= ").push_bind(date_to);
         }
 
-        // 只查询未过期的短链（expire_at 为 NULL 或大于当前时间）
-        qb.push(" AND (expire_at IS NULL OR expire_at > NOW())");
+        // 默认只查询未过期的短链（未到过期时间，且未超过闲置天数）
+        if !filter.include_expired {
+            qb.push(" AND NOT ").push(EXPIRED_CONDITION);
+        }
     }
 
     /// 构建返回数据
     fn to_view(src: LinkDto) -> LinkView {
-        let fmt = "%Y-%m-%d %H:%M:%S";
+        let fmt = VIEW_TIME_FORMAT;
         LinkView {
             id: src.id,
             user_id: src.user_id,
             short_code: src.short_code,
             long_url: src.long_url,
+            canonical_url: src.canonical_url,
             click_count: src.click_count,
+            max_clicks: src.max_clicks,
             expire_at: src.expire_at.map(|t| t.format(fmt).to_string()),
+            activate_at: src.activate_at.map(|t| t.format(fmt).to_string()),
+            inactivity_days: src.inactivity_days,
+            last_click_at: src.last_click_at.map(|t| t.format(fmt).to_string()),
+            expired_landing_url: src.expired_landing_url,
+            expired: src.expired != 0,
             created_at: src.created_at.format(fmt).to_string(),
         }
     }
@@ -461,58 +1652,414 @@ impl Link {
         limit: u64,
         offset: u64,
     ) -> Result<(Vec<LinkView>, i64), (StatusCode, String)> {
-        let mut data_qb: QueryBuilder<MySql> =
-            QueryBuilder::new("SELECT id, user_id, short_code, long_url, click_count, ");
+        let mut data_qb: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT id, user_id, short_code, long_url, canonical_url, click_count, max_clicks, ",
+        );
         data_qb
             .push("CONVERT_TZ(expire_at, 'UTC', ")
             .push_bind(&filter.timezone)
             .push(") AS expire_at, ")
+            .push("CONVERT_TZ(activate_at, 'UTC', ")
+            .push_bind(&filter.timezone)
+            .push(") AS activate_at, inactivity_days, ")
+            .push("CONVERT_TZ(last_click_at, 'UTC', ")
+            .push_bind(&filter.timezone)
+            .push(") AS last_click_at, expired_landing_url, IF(")
+            .push(EXPIRED_CONDITION)
+            .push(", 1, 0) AS expired, ")
             .push("CONVERT_TZ(created_at, 'UTC', ")
             .push_bind(&filter.timezone)
             .push(") AS created_at FROM links WHERE 1 = 1 ");
 
-        // 添加筛选条件
-        Self::apply_filters(&mut data_qb, filter);
+        // 添加筛选条件
+        Self::apply_filters(&mut data_qb, filter);
+
+        // 分页 & 排序
+        data_qb
+            .push(" ORDER BY created_at DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        // 编译执行
+        let rows = data_qb
+            .build_query_as::<LinkDto>()
+            .fetch_all(mysql_pool)
+            .await
+            .map_err(|e| {
+                warn!("find_links: DB select error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        let items = rows.into_iter().map(Self::to_view).collect();
+
+        // 统计总数
+        let mut count_qb: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT COUNT(*) FROM links WHERE 1 = 1 ");
+        Self::apply_filters(&mut count_qb, filter);
+        let count: i64 = count_qb
+            .build_query_scalar()
+            .fetch_one(mysql_pool)
+            .await
+            .map_err(|e| {
+                warn!("find_links: DB select error (count): {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        Ok((items, count))
+    }
+
+    /// 加行锁读取当前用户的短链，不存在或不属于当前用户返回 404
+    async fn lock_owned_link(
+        tx: &mut Transaction<'_, MySql>,
+        id: u64,
+        user_id: u64,
+    ) -> Result<LinkDto, (StatusCode, String)> {
+        let sql = format!(
+            "SELECT id, user_id, short_code, long_url, canonical_url, click_count, max_clicks, \
+             expire_at, activate_at, inactivity_days, last_click_at, expired_landing_url, \
+             IF({}, 1, 0) AS expired, created_at \
+             FROM links WHERE id = ? AND user_id = ? FOR UPDATE",
+            EXPIRED_CONDITION
+        );
+        let link: Option<LinkDto> = sqlx::query_as(&sql)
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(tx.as_mut())
+            .await
+            .map_err(|e| {
+                warn!("lock_owned_link: DB select error: id={}, err={}", id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        link.ok_or_else(|| {
+            warn!(
+                "lock_owned_link: 短链不存在: id={}, user_id={}",
+                id, user_id
+            );
+            (StatusCode::NOT_FOUND, "Link not found".into())
+        })
+    }
+
+    /// 重新读取短链当前是否已过期
+    async fn fetch_expired(
+        tx: &mut Transaction<'_, MySql>,
+        id: u64,
+    ) -> Result<bool, (StatusCode, String)> {
+        let sql = format!(
+            "SELECT IF({}, 1, 0) FROM links WHERE id = ?",
+            EXPIRED_CONDITION
+        );
+        let expired: i64 = sqlx::query_scalar(&sql)
+            .bind(id)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| {
+                warn!("fetch_expired: DB select error: id={}, err={}", id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        Ok(expired != 0)
+    }
+
+    /// 编辑短链（目标地址 / 过期时间 / 过期落地页）
+    ///
+    /// `long_url` 为 (原始地址, 规范形式)；`expire_at` 为 `Some(None)` 时改为永久有效；
+    /// `expired_landing_url` 为 `Some(None)` 时清除落地页；
+    /// 目标地址有变化时在同一事务内写入修改记录。返回修改后的短链（时间为 UTC）。
+    pub async fn update_link(
+        tx: &mut Transaction<'_, MySql>,
+        id: u64,
+        user_id: u64,
+        long_url: Option<(&str, &str)>,
+        expire_at: Option<Option<DateTime<Utc>>>,
+        expired_landing_url: Option<Option<&str>>,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let mut link = Self::lock_owned_link(tx, id, user_id).await?;
+
+        // 目标地址未变化时不写修改记录
+        let long_url = long_url.filter(|(url, _)| *url != link.long_url);
+        if long_url.is_none() && expire_at.is_none() && expired_landing_url.is_none() {
+            return Ok(Self::to_view(link));
+        }
+
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new("UPDATE links SET ");
+        let mut sep = qb.separated(", ");
+        if let Some((long_url, canonical_url)) = long_url {
+            sep.push("long_url = ").push_bind_unseparated(long_url);
+            sep.push("canonical_url = ")
+                .push_bind_unseparated(canonical_url);
+            sep.push("url_hash = ")
+                .push_bind_unseparated(url_hash(canonical_url));
+        }
+        if let Some(expire_at) = expire_at {
+            sep.push("expire_at = ").push_bind_unseparated(expire_at);
+            link.expire_at = expire_at.map(|t| t.naive_utc());
+        }
+        if let Some(landing_url) = expired_landing_url {
+            sep.push("expired_landing_url = ")
+                .push_bind_unseparated(landing_url);
+            link.expired_landing_url = landing_url.map(str::to_string);
+        }
+        qb.push(" WHERE id = ").push_bind(id);
+
+        qb.build().execute(tx.as_mut()).await.map_err(|e| {
+            warn!("update_link: DB update error: id={}, err={}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB update error: {}", e),
+            )
+        })?;
+        if expire_at.is_some() {
+            link.expired = Self::fetch_expired(tx, id).await? as i64;
+        }
+
+        if let Some((long_url, canonical_url)) = long_url {
+            Self::insert_revision(tx, id, user_id, "update", &link.long_url, long_url).await?;
+            link.long_url = long_url.to_string();
+            link.canonical_url = canonical_url.to_string();
+        }
+
+        Ok(Self::to_view(link))
+    }
+
+    /// 重新激活宽限期内的过期短链
+    ///
+    /// 已到过期时间的短链必须给出新的过期时间（`Some(None)` 为改为永久有效）；
+    /// 按闲置天数过期的短链把最近点击时间重置为当前时间，重新开始计算闲置天数。
+    pub async fn reactivate_link(
+        tx: &mut Transaction<'_, MySql>,
+        id: u64,
+        user_id: u64,
+        expire_at: Option<Option<DateTime<Utc>>>,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let mut link = Self::lock_owned_link(tx, id, user_id).await?;
+        if link.expired == 0 {
+            warn!("reactivate_link: 短链未过期: id={}", id);
+            return Err((StatusCode::BAD_REQUEST, "Link is not expired".into()));
+        }
+
+        let now = Utc::now();
+        // 没有闲置天数的短链只可能是到达了过期时间
+        let hard_expired =
+            link.inactivity_days.is_none() || link.expire_at.is_some_and(|t| t <= now.naive_utc());
+        if hard_expired && expire_at.is_none() {
+            warn!("reactivate_link: 缺少新的过期时间: id={}", id);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "A new expiry is required to reactivate this link".into(),
+            ));
+        }
+
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new("UPDATE links SET ");
+        let mut sep = qb.separated(", ");
+        if let Some(expire_at) = expire_at {
+            sep.push("expire_at = ").push_bind_unseparated(expire_at);
+            link.expire_at = expire_at.map(|t| t.naive_utc());
+        }
+        if link.inactivity_days.is_some() {
+            sep.push("last_click_at = ").push_bind_unseparated(now);
+            link.last_click_at = Some(now.naive_utc());
+        }
+        qb.push(" WHERE id = ").push_bind(id);
+
+        qb.build().execute(tx.as_mut()).await.map_err(|e| {
+            warn!("reactivate_link: DB update error: id={}, err={}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB update error: {}", e),
+            )
+        })?;
+        link.expired = Self::fetch_expired(tx, id).await? as i64;
+
+        Ok(Self::to_view(link))
+    }
+
+    /// 回滚目标地址：恢复为第 `revision` 次修改之前的地址，并记录一次 rollback
+    ///
+    /// `resolve_destination` 按当前规则重新计算规范形式并做安全检查，旧地址已不被允许时回滚失败。
+    pub async fn rollback_link(
+        tx: &mut Transaction<'_, MySql>,
+        id: u64,
+        user_id: u64,
+        revision: u32,
+        resolve_destination: impl FnOnce(&str) -> Result<String, (StatusCode, String)>,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let mut link = Self::lock_owned_link(tx, id, user_id).await?;
+
+        let target: Option<(String,)> = sqlx::query_as(
+            "SELECT old_long_url FROM link_revisions WHERE link_id = ? AND revision = ?",
+        )
+        .bind(id)
+        .bind(revision)
+        .fetch_optional(tx.as_mut())
+        .await
+        .map_err(|e| {
+            warn!("rollback_link: DB select error: id={}, err={}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB select error: {}", e),
+            )
+        })?;
 
-        // 分页 & 排序
-        data_qb
-            .push(" ORDER BY created_at DESC LIMIT ")
-            .push_bind(limit)
-            .push(" OFFSET ")
-            .push_bind(offset);
+        let Some((target,)) = target else {
+            warn!(
+                "rollback_link: 修改记录不存在: id={}, revision={}",
+                id, revision
+            );
+            return Err((StatusCode::NOT_FOUND, "Revision not found".into()));
+        };
 
-        // 编译执行
-        let rows = data_qb
-            .build_query_as::<LinkDto>()
-            .fetch_all(mysql_pool)
+        if target == link.long_url {
+            return Ok(Self::to_view(link));
+        }
+
+        let canonical_url = resolve_destination(&target)?;
+        sqlx::query("UPDATE links SET long_url = ?, canonical_url = ?, url_hash = ? WHERE id = ?")
+            .bind(&target)
+            .bind(&canonical_url)
+            .bind(url_hash(&canonical_url))
+            .bind(id)
+            .execute(tx.as_mut())
             .await
             .map_err(|e| {
-                warn!("find_links: DB select error: {}", e);
+                warn!("rollback_link: DB update error: id={}, err={}", id, e);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("DB select error: {}", e),
+                    format!("DB update error: {}", e),
                 )
             })?;
 
-        let items = rows.into_iter().map(Self::to_view).collect();
+        Self::insert_revision(tx, id, user_id, "rollback", &link.long_url, &target).await?;
+        link.long_url = target;
+        link.canonical_url = canonical_url;
 
-        // 统计总数
-        let mut count_qb: QueryBuilder<MySql> =
-            QueryBuilder::new("SELECT COUNT(*) FROM links WHERE 1 = 1 ");
-        Self::apply_filters(&mut count_qb, filter);
-        let count: i64 = count_qb
-            .build_query_scalar()
-            .fetch_one(mysql_pool)
+        Ok(Self::to_view(link))
+    }
+
+    /// 写入修改记录，调用方需已持有 links 行锁以保证版本号递增不冲突
+    async fn insert_revision(
+        tx: &mut Transaction<'_, MySql>,
+        link_id: u64,
+        user_id: u64,
+        action: &str,
+        old_long_url: &str,
+        new_long_url: &str,
+    ) -> Result<(), (StatusCode, String)> {
+        sqlx::query(
+            r#"INSERT INTO link_revisions
+                (link_id, revision, user_id, action, old_long_url, new_long_url)
+            SELECT ?, COALESCE(MAX(revision), 0) + 1, ?, ?, ?, ?
+            FROM link_revisions WHERE link_id = ?"#,
+        )
+        .bind(link_id)
+        .bind(user_id)
+        .bind(action)
+        .bind(old_long_url)
+        .bind(new_long_url)
+        .bind(link_id)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| {
+            warn!(
+                "insert_revision: DB insert error: link_id={}, err={}",
+                link_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB insert error: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// 查询短链的目标地址修改记录（按版本号倒序）
+    pub async fn find_revisions(
+        mysql_pool: &MySqlPool,
+        id: u64,
+        user_id: u64,
+    ) -> Result<Vec<LinkRevisionView>, (StatusCode, String)> {
+        let owned: Option<(u64,)> =
+            sqlx::query_as("SELECT id FROM links WHERE id = ? AND user_id = ?")
+                .bind(id)
+                .bind(user_id)
+                .fetch_optional(mysql_pool)
+                .await
+                .map_err(|e| {
+                    warn!("find_revisions: DB select error: id={}, err={}", id, e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB select error: {}", e),
+                    )
+                })?;
+        if owned.is_none() {
+            warn!("find_revisions: 短链不存在: id={}, user_id={}", id, user_id);
+            return Err((StatusCode::NOT_FOUND, "Link not found".into()));
+        }
+
+        let rows: Vec<LinkRevisionDto> = sqlx::query_as(
+            r#"SELECT revision, user_id, action, old_long_url, new_long_url, created_at
+            FROM link_revisions WHERE link_id = ? ORDER BY revision DESC"#,
+        )
+        .bind(id)
+        .fetch_all(mysql_pool)
+        .await
+        .map_err(|e| {
+            warn!("find_revisions: DB select error: id={}, err={}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB select error: {}", e),
+            )
+        })?;
+
+        let fmt = VIEW_TIME_FORMAT;
+        Ok(rows
+            .into_iter()
+            .map(|r| LinkRevisionView {
+                revision: r.revision,
+                user_id: r.user_id,
+                action: r.action,
+                old_long_url: r.old_long_url,
+                new_long_url: r.new_long_url,
+                created_at: r.created_at.format(fmt).to_string(),
+            })
+            .collect())
+    }
+
+    /// 删除短码的跳转缓存，下次访问回源 MySQL
+    pub async fn invalidate_shortlink(
+        conn: &mut Connection,
+        short_code: &str,
+    ) -> Result<(), (StatusCode, String)> {
+        let _: () = redis::cmd("UNLINK")
+            .arg(format!("shortlink:{}", short_code))
+            .query_async(conn)
             .await
             .map_err(|e| {
-                warn!("find_links: DB select error (count): {}", e);
+                warn!(
+                    "invalidate_shortlink: Redis unlink error: short_code={}, err={}",
+                    short_code, e
+                );
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("DB select error: {}", e),
+                    format!("Redis unlink error: {}", e),
                 )
             })?;
 
-        Ok((items, count))
+        Ok(())
     }
 
     /// 删除短链(手动)
@@ -576,95 +2123,386 @@ impl Link {
                 )
             })?;
 
-            // 构造并执行批量 UNLINK
-            let mut pipe = redis::pipe();
-            pipe.atomic();
-            for (code,) in &short_codes {
-                pipe.cmd("UNLINK")
-                    .arg(format!("shortlink:{}", code))
-                    .ignore();
-                pipe.cmd("UNLINK")
-                    .arg(format!("shortlink_click:{}", code))
-                    .ignore();
-            }
-            let _: () = pipe.query_async(conn).await.map_err(|e| {
-                warn!("delete_links: Redis unlink error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Redis unlink error: {}", e),
-                )
-            })?;
+            // 清理 Redis 缓存
+            let codes: Vec<String> = short_codes.into_iter().map(|(code,)| code).collect();
+            Self::purge_code_cache(conn, &codes).await?;
         }
 
         Ok(())
     }
 
-    /// 过期短链删除(定时任务)
-    pub async fn delete_expired_links(
-        mysql_pool: &MySqlPool,
+    /// 清理已删除短码的 Redis 缓存与待同步点击量（包括暂存中的同步批次）
+    async fn purge_code_cache(
         conn: &mut Connection,
+        codes: &[String],
     ) -> Result<(), (StatusCode, String)> {
-        // 先记录待删除的短码，方便清理缓存和访问日志
-        let rows: Vec<(String,)> = sqlx::query_as(
-            "SELECT short_code FROM links WHERE expire_at < NOW() AND short_code IS NOT NULL",
-        )
-        .fetch_all(mysql_pool)
-        .await
-        .map_err(|e| {
-            warn!("delete_expired_links: DB select short_code error: {}", e);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for code in codes {
+            pipe.cmd("UNLINK")
+                .arg(format!("shortlink:{}", code))
+                .arg(format!("{}{}", CLICK_BUDGET_PREFIX, code))
+                .ignore();
+            pipe.hdel(CLICK_COUNTS_KEY, code)
+                .ignore()
+                .hdel(LAST_CLICK_KEY, code)
+                .ignore()
+                .hdel(CLICK_INFLIGHT_KEY, code)
+                .ignore()
+                .hdel(LAST_CLICK_INFLIGHT_KEY, code)
+                .ignore()
+                .srem(CLICK_DIRTY_KEY, code)
+                .ignore();
+        }
+        let _: () = pipe.query_async(conn).await.map_err(|e| {
+            warn!("purge_code_cache: Redis unlink error: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("DB select error: {}", e),
+                format!("Redis unlink error: {}", e),
             )
         })?;
-        let expired_codes: Vec<String> = rows.into_iter().map(|(code,)| code).collect();
 
-        // 构造并执行批量 DELETE
-        let mut qb = QueryBuilder::new("DELETE FROM links WHERE expire_at < NOW()");
-        qb.build().execute(mysql_pool).await.map_err(|e| {
-            warn!("delete_expired_links: DB Delete error: {}", e);
+        Ok(())
+    }
+
+    /// 拼接可清理条件：过期已超过宽限期 `grace_period`（秒）
+    fn push_purge_condition(qb: &mut QueryBuilder<'_, MySql>, grace_period: i64) {
+        qb.push("(expire_at < NOW() - INTERVAL ")
+            .push_bind(grace_period)
+            .push(
+                " SECOND OR (inactivity_days IS NOT NULL AND COALESCE(last_click_at, created_at) \
+                   < NOW() - INTERVAL inactivity_days DAY - INTERVAL ",
+            )
+            .push_bind(grace_period)
+            .push(" SECOND))");
+    }
+
+    /// 分批删除指定短码的访问日志，每条语句最多 `log_batch` 行，避免单条语句过大、锁持有过久
+    ///
+    /// 只删除已没有对应短链的日志（短码仍存在时跳过），返回删除的行数。
+    async fn delete_orphan_visit_logs(
+        mysql_pool: &MySqlPool,
+        codes: &[String],
+        log_batch: u64,
+    ) -> Result<u64, (StatusCode, String)> {
+        let mut deleted = 0;
+        loop {
+            let mut qb: QueryBuilder<MySql> =
+                QueryBuilder::new("DELETE FROM visit_logs WHERE short_code IN (");
+            let mut sep = qb.separated(", ");
+            for code in codes {
+                sep.push_bind(code);
+            }
+            qb.push(
+                ") AND NOT EXISTS \
+                 (SELECT 1 FROM links WHERE links.short_code = visit_logs.short_code) LIMIT ",
+            )
+            .push_bind(log_batch);
+            let affected = qb
+                .build()
+                .execute(mysql_pool)
+                .await
+                .map_err(|e| {
+                    warn!("delete_orphan_visit_logs: DB Delete error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB Delete error: {}", e),
+                    )
+                })?
+                .rows_affected();
+            deleted += affected;
+            if affected < log_batch {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    /// 清理上次中断遗留的访问日志及其 Redis 残留
+    ///
+    /// 删除过期短链的事务提交前先把短码记入 `PURGE_PENDING_KEY`，日志和缓存清理完才移除；
+    /// 期间中断（进程退出、MySQL 出错）时短码留在集合中，由这里每次取 `chunk_size` 个补删。
+    /// 事务未提交的短码对应短链仍然存在，只移出集合，不清理缓存。
+    async fn sweep_orphan_visit_logs(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        chunk_size: u64,
+        log_batch: u64,
+    ) -> Result<u64, (StatusCode, String)> {
+        let mut deleted = 0;
+        loop {
+            let codes: Vec<String> = redis::cmd("SRANDMEMBER")
+                .arg(PURGE_PENDING_KEY)
+                .arg(chunk_size)
+                .query_async(conn)
+                .await
+                .map_err(|e| {
+                    warn!("sweep_orphan_visit_logs: Redis SRANDMEMBER error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Redis SRANDMEMBER error: {}", e),
+                    )
+                })?;
+            if codes.is_empty() {
+                break;
+            }
+
+            let mut qb: QueryBuilder<MySql> =
+                QueryBuilder::new("SELECT short_code FROM links WHERE short_code IN (");
+            let mut sep = qb.separated(", ");
+            for code in &codes {
+                sep.push_bind(code);
+            }
+            qb.push(")");
+            let alive: Vec<(String,)> =
+                qb.build_query_as()
+                    .fetch_all(mysql_pool)
+                    .await
+                    .map_err(|e| {
+                        warn!("sweep_orphan_visit_logs: DB select error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("DB select error: {}", e),
+                        )
+                    })?;
+            let alive: HashSet<String> = alive.into_iter().map(|(code,)| code).collect();
+            let orphans: Vec<String> = codes
+                .iter()
+                .filter(|code| !alive.contains(*code))
+                .cloned()
+                .collect();
+
+            if !orphans.is_empty() {
+                deleted += Self::delete_orphan_visit_logs(mysql_pool, &orphans, log_batch).await?;
+                Self::purge_code_cache(conn, &orphans).await?;
+            }
+            Self::clear_purge_pending(conn, &codes).await?;
+        }
+
+        if deleted > 0 {
+            info!(
+                "sweep_orphan_visit_logs: 已清理遗留访问日志: visit_logs={}",
+                deleted
+            );
+        }
+        Ok(deleted)
+    }
+
+    /// 把短码移出待清理集合
+    async fn clear_purge_pending(
+        conn: &mut Connection,
+        codes: &[String],
+    ) -> Result<(), (StatusCode, String)> {
+        let _: () = conn.srem(PURGE_PENDING_KEY, codes).await.map_err(|e| {
+            warn!("clear_purge_pending: Redis SREM error: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("DB Delete error: {}", e),
+                format!("Redis SREM error: {}", e),
             )
         })?;
 
-        if !expired_codes.is_empty() {
-            // 清理访问日志
-            let mut logs_qb = QueryBuilder::new("DELETE FROM visit_logs WHERE short_code IN (");
-            let mut sep = logs_qb.separated(", ");
-            for code in &expired_codes {
-                sep.push_bind(code);
+        Ok(())
+    }
+
+    /// 过期短链删除(定时任务)
+    ///
+    /// 只删除过期已超过宽限期 `grace_period`（秒）的短链，宽限期内的保留数据以便重新激活。
+    /// 按 id 做键集分页，每次取 `chunk_size` 条过期短链：事务内加锁复核、校验租约围栏令牌 `fence` 后删除 links，
+    /// 再按每次最多 `log_batch` 行分批删除访问日志并清理 Redis。单次最多处理 `max_chunks` 块，
+    /// 处理进度（最后的 id）保存在 Redis，下次从断点继续，扫到末尾后从头开始新一轮；
+    /// 每次开始前先补删上次中断遗留的访问日志。
+    pub async fn delete_expired_links(
+        mysql_pool: &MySqlPool,
+        conn: &mut Connection,
+        fence: Fence,
+        grace_period: i64,
+        chunk_size: u64,
+        max_chunks: u64,
+        log_batch: u64,
+    ) -> Result<(), (StatusCode, String)> {
+        let mut cursor: u64 = conn
+            .get::<_, Option<u64>>(PURGE_CURSOR_KEY)
+            .await
+            .map_err(|e| {
+                warn!("delete_expired_links: Redis get cursor error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis get error: {}", e),
+                )
+            })?
+            .unwrap_or(0);
+        let (mut deleted_links, mut deleted_logs) = (0_u64, 0_u64);
+
+        // 补删上次删除短链后、清理访问日志前中断遗留的日志
+        deleted_logs +=
+            Self::sweep_orphan_visit_logs(mysql_pool, conn, chunk_size, log_batch).await?;
+
+        for chunk in 0..max_chunks {
+            // 取出下一块候选
+            let mut qb: QueryBuilder<MySql> = QueryBuilder::new("SELECT id FROM links WHERE id > ");
+            qb.push_bind(cursor).push(" AND ");
+            Self::push_purge_condition(&mut qb, grace_period);
+            qb.push(" ORDER BY id LIMIT ").push_bind(chunk_size);
+            let candidates: Vec<(u64,)> =
+                qb.build_query_as()
+                    .fetch_all(mysql_pool)
+                    .await
+                    .map_err(|e| {
+                        warn!("delete_expired_links: DB select error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("DB select error: {}", e),
+                        )
+                    })?;
+
+            // 扫到末尾，下次从头开始
+            let Some(&(last_id,)) = candidates.last() else {
+                cursor = 0;
+                break;
+            };
+
+            // 加锁复核仍然过期（期间可能被续期）后删除
+            let mut tx = mysql_pool.begin().await.map_err(|e| {
+                warn!("delete_expired_links: DB Begin error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB Begin error: {}", e),
+                )
+            })?;
+
+            let mut qb: QueryBuilder<MySql> =
+                QueryBuilder::new("SELECT id, short_code FROM links WHERE ");
+            Self::push_purge_condition(&mut qb, grace_period);
+            qb.push(" AND id IN (");
+            let mut sep = qb.separated(", ");
+            for (id,) in &candidates {
+                sep.push_bind(id);
+            }
+            qb.push(") FOR UPDATE");
+            let mut expired: Vec<(u64, Option<String>)> = qb
+                .build_query_as()
+                .fetch_all(tx.as_mut())
+                .await
+                .map_err(|e| {
+                    warn!("delete_expired_links: DB select for update error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB select error: {}", e),
+                    )
+                })?;
+
+            // 有尚未同步（含同步中暂存）的点击说明最近仍在使用（last_click_at 还没更新），本轮跳过
+            let codes: Vec<&String> = expired.iter().filter_map(|(_, c)| c.as_ref()).collect();
+            if !codes.is_empty() {
+                let (pending, inflight): (Vec<Option<i64>>, Vec<Option<i64>>) = redis::pipe()
+                    .cmd("HMGET")
+                    .arg(LAST_CLICK_KEY)
+                    .arg(&codes)
+                    .cmd("HMGET")
+                    .arg(LAST_CLICK_INFLIGHT_KEY)
+                    .arg(&codes)
+                    .query_async(conn)
+                    .await
+                    .map_err(|e| {
+                        warn!("delete_expired_links: Redis HMGET error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Redis HMGET error: {}", e),
+                        )
+                    })?;
+                let active: HashSet<String> = codes
+                    .into_iter()
+                    .zip(pending.into_iter().zip(inflight))
+                    .filter(|(_, (ts, staged))| ts.is_some() || staged.is_some_and(|t| t > 0))
+                    .map(|(code, _)| code.clone())
+                    .collect();
+                expired.retain(|(_, code)| code.as_ref().is_none_or(|c| !active.contains(c)));
+            }
+
+            if !expired.is_empty() {
+                fence.check(&mut tx).await?;
+                let mut qb: QueryBuilder<MySql> =
+                    QueryBuilder::new("DELETE FROM links WHERE id IN (");
+                let mut sep = qb.separated(", ");
+                for (id, _) in &expired {
+                    sep.push_bind(id);
+                }
+                qb.push(")");
+                qb.build().execute(tx.as_mut()).await.map_err(|e| {
+                    warn!("delete_expired_links: DB Delete error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("DB Delete error: {}", e),
+                    )
+                })?;
+
+                // 提交前记下待清理的短码，提交后中断时由下次清理补删
+                let codes: Vec<&String> = expired.iter().filter_map(|(_, c)| c.as_ref()).collect();
+                if !codes.is_empty() {
+                    let _: () = conn.sadd(PURGE_PENDING_KEY, &codes).await.map_err(|e| {
+                        warn!("delete_expired_links: Redis SADD error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Redis SADD error: {}", e),
+                        )
+                    })?;
+                }
             }
-            logs_qb.push(")");
-            logs_qb.build().execute(mysql_pool).await.map_err(|e| {
-                warn!("delete_expired_links: visit_logs delete error: {}", e);
+
+            tx.commit().await.map_err(|e| {
+                warn!("delete_expired_links: DB Commit error: {}", e);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("DB Delete error: {}", e),
+                    format!("DB Commit error: {}", e),
                 )
             })?;
 
-            // 清理 Redis 缓存
-            let mut pipe = redis::pipe();
-            pipe.atomic();
-            for code in &expired_codes {
-                pipe.cmd("UNLINK")
-                    .arg(format!("shortlink:{}", code))
-                    .ignore();
-                pipe.cmd("UNLINK")
-                    .arg(format!("shortlink_click:{}", code))
-                    .ignore();
+            let codes: Vec<String> = expired.iter().filter_map(|(_, c)| c.clone()).collect();
+            deleted_links += expired.len() as u64;
+
+            if !codes.is_empty() {
+                deleted_logs +=
+                    Self::delete_orphan_visit_logs(mysql_pool, &codes, log_batch).await?;
+
+                // 清理 Redis 缓存
+                Self::purge_code_cache(conn, &codes).await?;
+                Self::clear_purge_pending(conn, &codes).await?;
             }
-            let _: () = pipe.query_async(conn).await.map_err(|e| {
-                warn!("delete_expired_links: Redis unlink error: {}", e);
+
+            // 记录断点
+            cursor = last_id;
+            let _: () = conn.set(PURGE_CURSOR_KEY, cursor).await.map_err(|e| {
+                warn!("delete_expired_links: Redis set cursor error: {}", e);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Redis unlink error: {}", e),
+                    format!("Redis set error: {}", e),
+                )
+            })?;
+
+            info!(
+                "delete_expired_links: chunk {}/{} done, cursor={}, links={}, visit_logs={}",
+                chunk + 1,
+                max_chunks,
+                cursor,
+                deleted_links,
+                deleted_logs
+            );
+        }
+
+        if cursor == 0 {
+            let _: () = conn.del(PURGE_CURSOR_KEY).await.map_err(|e| {
+                warn!("delete_expired_links: Redis del cursor error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis del error: {}", e),
                 )
             })?;
         }
 
+        info!(
+            "delete_expired_links: run finished, cursor={}, links={}, visit_logs={}",
+            cursor, deleted_links, deleted_logs
+        );
+
         Ok(())
     }
 
@@ -781,3 +2619,50 @@ impl Link {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_row(long_url: &str) -> LinkInsert<'_> {
+        LinkInsert {
+            long_url,
+            canonical_url: long_url,
+            expire_at: None,
+            activate_at: None,
+            inactivity_days: None,
+            max_clicks: None,
+            password_hash: None,
+            expired_landing_url: None,
+        }
+    }
+
+    /// 需要 MySQL：设置 `DATABASE_URL` 后执行 `cargo test -- --ignored`
+    #[sqlx::test(migrations = "../common/migrations")]
+    #[ignore = "requires MySQL (DATABASE_URL)"]
+    async fn test_short_code_case_sensitive(pool: MySqlPool) {
+        let url = "https://example.com/a";
+        let mut tx = pool.begin().await.unwrap();
+        let ids = Link::insert_long_urls(&mut tx, &[insert_row(url), insert_row(url)], 1)
+            .await
+            .unwrap();
+        Link::update_short_codes(
+            &mut tx,
+            &[(ids[0], "abcXYZ".into()), (ids[1], "ABCxyz".into())],
+        )
+        .await
+        .unwrap();
+
+        // short_code 能解码为 String，且只匹配大小写完全相同的短码
+        let found = Link::find_existing_codes(&mut tx, &["abcXYZ".into(), "abcxyz".into()])
+            .await
+            .unwrap();
+        assert_eq!(found, HashSet::from(["abcXYZ".to_string()]));
+        tx.commit().await.unwrap();
+
+        let reusable = Link::find_reusable_link(&pool, 1, url, url, None)
+            .await
+            .unwrap();
+        assert_eq!(reusable.map(|(code, _)| code).as_deref(), Some("ABCxyz"));
+    }
+}
@@ -1,2 +1,8 @@
 pub mod background_jobs;
+pub mod code_policy;
+pub mod destination_policy;
+pub mod job_lease;
+pub mod link_password;
+pub mod short_code;
 pub mod shortlinks;
+pub mod url_normalize;
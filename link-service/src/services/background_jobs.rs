@@ -1,10 +1,27 @@
 use crate::{
     models::Link,
-    services::shortlinks::ShortlinkService,
+    services::{
+        job_lease::{Fence, JobLease},
+        shortlinks::ShortlinkService,
+    },
     state::{AppState, ScheduledJobKind},
 };
-use std::sync::Arc;
-use tokio::sync::{Semaphore, mpsc::Receiver};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::{
+    future::Future,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{
+        Semaphore,
+        mpsc::{Receiver, error::TrySendError},
+        watch,
+    },
+    task::JoinHandle,
+};
 use tracing::{info, warn};
 
 /// 丢给后台的作业类型
@@ -17,12 +34,15 @@ pub enum BackgroundJob {
         ip: String,
         user_agent: String,
         referer: String,
+        /// 点击量已在扣减限次短链剩余次数时记入，只推送访问日志
+        counted: bool,
     },
     /// 设置点击量和缓存
     SetClickCount {
         short_code: String,
         long_url: String,
         cache_ttl: i64,
+        activate_at: Option<DateTime<Utc>>,
     },
     /// 启动点击量同步
     SpawnClickCountSync,
@@ -32,103 +52,519 @@ pub enum BackgroundJob {
     SpawnExpiredLinksDelete,
 }
 
-/// 启动后台“固定并发 N + 有界队列”，返回用于投递作业的 tx
+/// 启动后台“固定并发 N + 有界队列”
+///
+/// `shutdown` 变为 `true` 后停止接收新作业，在 `shutdown_timeout_ms` 内处理完队列和溢出缓冲区
+/// 中剩余的点击/日志作业并等待执行中的作业结束，最后再同步一次点击量和访问日志。
+/// 返回的句柄在上述流程结束后完成。
 pub fn spawn_background_workers(
     state: Arc<AppState>,
     mut rx: Receiver<BackgroundJob>,
     max_concurrency: usize,
-) {
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
     let sem = Arc::new(Semaphore::new(max_concurrency));
 
     // 一个调度任务：串行从队列取活，按最多 N 并发派发
-    tokio::spawn({
-        async move {
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                job = rx.recv() => match job {
+                    Some(job) => dispatch(&state, &sem, job).await,
+                    None => break,
+                },
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() || shutdown.has_changed().is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // 停止接收新作业（之后的 try_send 返回 Closed，请求内直接写 Redis）
+        rx.close();
+        let timeout = Duration::from_millis(state.config.read().await.shutdown_timeout_ms);
+        info!("bg_jobs: draining, timeout={:?}", timeout);
+
+        let drained = tokio::time::timeout(timeout, async {
+            let mut jobs = Vec::new();
             while let Some(job) = rx.recv().await {
-                let state = state.clone();
-                // 限制同时活跃任务数
-                let sem = sem.clone();
-                let permit = sem.acquire_owned().await.expect("semaphore closed");
-
-                tokio::spawn(async move {
-                    let _permit = permit;
-                    // 每个作业自己从池里取连接；失败就告警返回
-                    let mut conn = match state.redis_pool.get().await {
-                        Ok(c) => c,
-                        Err(e) => {
-                            warn!("bg_redis: redis_pool.get() failed: {e}");
-                            return;
-                        }
-                    };
-                    match job {
-                        BackgroundJob::PushClickAndLog {
-                            // 推送点击量和访问日志
-                            short_code,
-                            long_url,
-                            ip,
-                            user_agent,
-                            referer,
-                        } => {
-                            ShortlinkService::push_click_and_log(
-                                &mut conn, short_code, long_url, ip, user_agent, referer,
-                            )
-                            .await;
-                        }
-                        BackgroundJob::SetClickCount {
-                            // 设置点击量和缓存
-                            short_code,
-                            long_url,
-                            cache_ttl,
-                        } => {
-                            if let Err(e) =
-                                Link::set_shortlink(&mut conn, &short_code, &long_url, cache_ttl)
-                                    .await
-                            {
-                                warn!("create_shortlink: Redis set_shortlink error: {:?}", e);
-                            }
-
-                            // 设置点击量
-                            if let Err(e) =
-                                Link::set_click_count(&mut conn, &short_code, cache_ttl).await
-                            {
-                                warn!("create_shortlink: Redis set_click_count error: {:?}", e);
-                            }
-                        }
-                        BackgroundJob::SpawnClickCountSync => {
-                            // 启动点击量同步
-                            info!("Syncing click counts start");
-                            if let Err(e) =
-                                Link::sync_click_counts(&state.mysql_pool, &mut conn, 100).await
-                            {
-                                warn!("Failed to sync click counts: {:?}", e);
-                            }
-                            state.pending_set.remove(&ScheduledJobKind::SyncClick);
-                            info!("Synced click counts end");
-                        }
-                        BackgroundJob::SpawnVisitLogSync => {
-                            // 启动访问日志同步
-                            info!("Syncing visit logs start");
-                            if let Err(e) =
-                                Link::sync_visit_logs(&state.mysql_pool, &mut conn, 100).await
-                            {
-                                warn!("Failed to sync visit logs: {:?}", e);
-                            }
-                            state.pending_set.remove(&ScheduledJobKind::SyncVisitLog);
-                            info!("Synced visit logs end");
-                        }
-                        BackgroundJob::SpawnExpiredLinksDelete => {
-                            // 启动过期短链删除
-                            info!("Syncing expired links start");
-                            if let Err(e) =
-                                Link::delete_expired_links(&state.mysql_pool, &mut conn).await
-                            {
-                                warn!("Failed to delete expired links: {:?}", e);
-                            }
-                            state.pending_set.remove(&ScheduledJobKind::DeleteExpired);
-                            info!("Synced expired links end");
-                        }
-                    };
-                });
+                jobs.push(job);
+            }
+            jobs.extend(
+                state
+                    .bg_spill
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .drain(..),
+            );
+            for job in jobs {
+                // 定时作业不再执行，退出前统一做最后一次同步
+                if !matches!(
+                    job,
+                    BackgroundJob::SpawnClickCountSync
+                        | BackgroundJob::SpawnVisitLogSync
+                        | BackgroundJob::SpawnExpiredLinksDelete
+                ) {
+                    dispatch(&state, &sem, job).await;
+                }
             }
+            // 拿到全部许可即所有执行中的作业已结束
+            let _ = sem.acquire_many(max_concurrency as u32).await;
+        })
+        .await;
+        if drained.is_err() {
+            warn!("bg_jobs: drain timed out after {:?}", timeout);
         }
+
+        // 点击量同步取出增量后不能中途取消，只在批次之间检查截止时间
+        let deadline = Instant::now() + timeout;
+        run_scheduled(&state, ScheduledJobKind::SyncClick, Some(deadline)).await;
+        // 访问日志写入提交后才确认，超时取消不会丢失
+        let flushed = tokio::time::timeout(
+            deadline.saturating_duration_since(Instant::now()),
+            run_scheduled(&state, ScheduledJobKind::SyncVisitLog, None),
+        )
+        .await;
+        if flushed.is_err() {
+            warn!("bg_jobs: final flush timed out after {:?}", timeout);
+        }
+        info!("bg_jobs: stopped");
+    })
+}
+
+/// 按最多 N 并发派发单个作业
+async fn dispatch(state: &Arc<AppState>, sem: &Arc<Semaphore>, job: BackgroundJob) {
+    let state = state.clone();
+    // 限制同时活跃任务数
+    let permit = sem.clone().acquire_owned().await.expect("semaphore closed");
+
+    tokio::spawn(async move {
+        let _permit = permit;
+        // 定时作业自己管理连接和执行标记
+        let kind = match job {
+            BackgroundJob::SpawnClickCountSync => Some(ScheduledJobKind::SyncClick),
+            BackgroundJob::SpawnVisitLogSync => Some(ScheduledJobKind::SyncVisitLog),
+            BackgroundJob::SpawnExpiredLinksDelete => Some(ScheduledJobKind::DeleteExpired),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            run_scheduled(&state, kind, None).await;
+            return;
+        }
+
+        // 每个作业自己从池里取连接；失败就告警返回
+        let mut conn = match state.redis_pool.get().await {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("bg_redis: redis_pool.get() failed: {e}");
+                return;
+            }
+        };
+        match job {
+            BackgroundJob::PushClickAndLog {
+                // 推送点击量和访问日志
+                short_code,
+                long_url,
+                ip,
+                user_agent,
+                referer,
+                counted,
+            } => {
+                if let Err(e) = ShortlinkService::push_click_and_log(
+                    &mut conn, short_code, long_url, ip, user_agent, referer, counted,
+                )
+                .await
+                {
+                    warn!("bg_redis: push_click_and_log error: {:?}", e);
+                }
+            }
+            BackgroundJob::SetClickCount {
+                // 设置点击量和缓存
+                short_code,
+                long_url,
+                cache_ttl,
+                activate_at,
+            } => {
+                if let Err(e) =
+                    Link::set_shortlink(&mut conn, &short_code, &long_url, cache_ttl, activate_at)
+                        .await
+                {
+                    warn!("create_shortlink: Redis set_shortlink error: {:?}", e);
+                }
+
+                // 设置点击量
+                if let Err(e) = Link::set_click_count(&mut conn, &short_code).await {
+                    warn!("create_shortlink: Redis set_click_count error: {:?}", e);
+                }
+            }
+            // 定时作业已在上面处理
+            BackgroundJob::SpawnClickCountSync
+            | BackgroundJob::SpawnVisitLogSync
+            | BackgroundJob::SpawnExpiredLinksDelete => {}
+        };
     });
 }
+
+/// 定时作业的执行标记，离开作用域（含提前返回、被取消）时从 `pending_set` 移除，
+/// 调度器才会投递下一轮
+struct PendingGuard<'a> {
+    state: &'a AppState,
+    kind: ScheduledJobKind,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.state.pending_set.remove(&self.kind);
+    }
+}
+
+/// 在租约保护下执行一次定时作业
+///
+/// `deadline` 只对点击量同步生效：到达后处理完当前批次即停止，剩余的留给下次同步。
+async fn run_scheduled(state: &AppState, kind: ScheduledJobKind, deadline: Option<Instant>) {
+    let _pending = PendingGuard { state, kind };
+    let mut conn = match state.redis_pool.get().await {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("bg_redis: redis_pool.get() failed: {e}");
+            return;
+        }
+    };
+    let conn = &mut conn;
+
+    match kind {
+        ScheduledJobKind::SyncClick => {
+            run_leased(state, kind, |fence| {
+                Link::sync_click_counts(&state.mysql_pool, conn, 100, fence, deadline)
+            })
+            .await;
+        }
+        ScheduledJobKind::SyncVisitLog => {
+            let (batch, max_rows, claim_idle_ms, max_deliveries) = {
+                let cfg = state.config.read().await;
+                (
+                    cfg.visit_log_sync_batch_size,
+                    cfg.visit_log_insert_max_rows,
+                    cfg.visit_log_claim_idle_ms,
+                    cfg.visit_log_max_deliveries,
+                )
+            };
+            // 访问日志按 Stream 条目 ID 幂等写入，不需要校验 fence
+            run_leased(state, kind, |_| {
+                Link::sync_visit_logs(
+                    &state.mysql_pool,
+                    conn,
+                    &state.instance_id,
+                    batch,
+                    max_rows,
+                    claim_idle_ms,
+                    max_deliveries,
+                )
+            })
+            .await;
+        }
+        ScheduledJobKind::DeleteExpired => {
+            let (grace_period, chunk_size, max_chunks, log_batch) = {
+                let cfg = state.config.read().await;
+                (
+                    cfg.shortlink_expiry_grace_period,
+                    cfg.bg_purge_chunk_size,
+                    cfg.bg_purge_max_chunks,
+                    cfg.bg_purge_log_batch_size,
+                )
+            };
+            run_leased(state, kind, |fence| {
+                Link::delete_expired_links(
+                    &state.mysql_pool,
+                    conn,
+                    fence,
+                    grace_period,
+                    chunk_size,
+                    max_chunks,
+                    log_batch,
+                )
+            })
+            .await;
+        }
+    }
+}
+
+impl ScheduledJobKind {
+    /// 作业名，用于 Redis 租约 key
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduledJobKind::SyncClick => "sync_click",
+            ScheduledJobKind::SyncVisitLog => "sync_visit_log",
+            ScheduledJobKind::DeleteExpired => "delete_expired",
+        }
+    }
+
+    /// 对应投递给后台的作业
+    fn job(&self) -> BackgroundJob {
+        match self {
+            ScheduledJobKind::SyncClick => BackgroundJob::SpawnClickCountSync,
+            ScheduledJobKind::SyncVisitLog => BackgroundJob::SpawnVisitLogSync,
+            ScheduledJobKind::DeleteExpired => BackgroundJob::SpawnExpiredLinksDelete,
+        }
+    }
+}
+
+/// 持有跨副本租约执行定时作业
+///
+/// 抢不到租约说明其他副本正在执行，本轮直接跳过；执行期间按 ttl/3 续约，
+/// 续约被拒绝或连续失败超过 ttl 视为租约丢失，立即中断作业（未提交的事务随之回滚）。
+/// `work` 拿到本次租约的围栏令牌，在写入前校验，防止暂停后恢复的旧持有者提交。
+async fn run_leased<W, F>(state: &AppState, kind: ScheduledJobKind, work: W)
+where
+    W: FnOnce(Fence) -> F,
+    F: Future<Output = Result<(), (StatusCode, String)>>,
+{
+    let ttl_ms = state.config.read().await.bg_job_lease_ttl_ms;
+
+    let lease = {
+        let mut conn = match state.redis_pool.get().await {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("bg_redis: redis_pool.get() failed: {e}");
+                return;
+            }
+        };
+        match JobLease::try_acquire(&mut conn, kind, &state.instance_id, ttl_ms).await {
+            Ok(Some(lease)) => lease,
+            Ok(None) => {
+                info!("{:?}: lease held by another replica, skip", kind);
+                return;
+            }
+            Err(e) => {
+                warn!("{:?}: failed to acquire lease: {:?}", kind, e);
+                return;
+            }
+        }
+    };
+
+    let keep_alive = async {
+        let ttl = Duration::from_millis(lease.ttl_ms);
+        let mut ticker = tokio::time::interval(ttl / 3);
+        ticker.tick().await;
+        let mut last_renewed = Instant::now();
+        loop {
+            ticker.tick().await;
+            let renewed = match state.redis_pool.get().await {
+                Ok(mut conn) => lease.renew(&mut conn).await,
+                Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+            };
+            match renewed {
+                Ok(true) => last_renewed = Instant::now(),
+                Ok(false) => return,
+                Err(e) => {
+                    warn!("{:?}: lease renew failed: {:?}", kind, e);
+                    if last_renewed.elapsed() >= ttl {
+                        return;
+                    }
+                }
+            }
+        }
+    };
+
+    info!("{:?} start, fence={}", kind, lease.fence);
+    tokio::select! {
+        res = work(lease.fencing()) => {
+            if let Err(e) = res {
+                warn!("{:?} failed: {:?}", kind, e);
+            }
+            info!("{:?} end, fence={}", kind, lease.fence);
+        }
+        _ = keep_alive => {
+            warn!("{:?}: lease lost, aborted, fence={}", kind, lease.fence);
+        }
+    }
+
+    match state.redis_pool.get().await {
+        Ok(mut conn) => {
+            if let Err(e) = lease.release(&mut conn).await {
+                warn!("{:?}: failed to release lease: {:?}", kind, e);
+            }
+        }
+        Err(e) => warn!("bg_redis: redis_pool.get() failed: {e}"),
+    }
+}
+
+/// 启动定时调度：按配置的间隔周期性投递点击量同步、访问日志同步和过期短链删除作业
+///
+/// `shutdown` 变为 `true`（或发送端被丢弃）时所有调度循环退出，返回的句柄在全部退出后结束。
+pub fn spawn_scheduler(state: Arc<AppState>, shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let (schedules, max_jitter_ms) = {
+            let cfg = state.config.read().await;
+            (
+                [
+                    (
+                        ScheduledJobKind::SyncClick,
+                        cfg.bg_click_counts_sync_interval,
+                    ),
+                    (
+                        ScheduledJobKind::SyncVisitLog,
+                        cfg.bg_visit_logs_sync_interval,
+                    ),
+                    (
+                        ScheduledJobKind::DeleteExpired,
+                        cfg.bg_expired_links_sync_interval,
+                    ),
+                ],
+                cfg.bg_jobs_max_jitter_ms,
+            )
+        };
+
+        let mut handles = Vec::with_capacity(schedules.len());
+        for (kind, interval_secs) in schedules {
+            // 间隔为 0 视为关闭该任务
+            if interval_secs == 0 {
+                warn!("scheduler: {:?} interval is 0, disabled", kind);
+                continue;
+            }
+            handles.push(tokio::spawn(run_schedule(
+                state.clone(),
+                kind,
+                Duration::from_secs(interval_secs),
+                max_jitter_ms,
+                shutdown.clone(),
+            )));
+        }
+
+        for handle in handles {
+            if let Err(e) = handle.await {
+                warn!("scheduler: schedule task panicked: {e}");
+            }
+        }
+        info!("scheduler: stopped");
+    })
+}
+
+/// 单个定时任务的调度循环
+async fn run_schedule(
+    state: Arc<AppState>,
+    kind: ScheduledJobKind,
+    interval: Duration,
+    max_jitter_ms: u64,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    info!("scheduler: {:?} every {:?}", kind, interval);
+    loop {
+        if *shutdown.borrow() {
+            break;
+        }
+
+        // 加随机抖动，避免多个任务（或多个副本）在同一时刻集中触发
+        let jitter = if max_jitter_ms > 0 {
+            Duration::from_millis(rand::thread_rng().gen_range(0..=max_jitter_ms))
+        } else {
+            Duration::ZERO
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval + jitter) => {}
+            // 收到关闭信号或发送端已丢弃
+            _ = shutdown.changed() => {}
+        }
+        if *shutdown.borrow() || shutdown.has_changed().is_err() {
+            break;
+        }
+
+        // 上一次还在执行（或仍在队列中）则跳过本轮，执行完成后由后台作业移除标记
+        if !state.pending_set.insert(kind) {
+            info!("scheduler: {:?} still pending, skip this round", kind);
+            continue;
+        }
+
+        if let Err(e) = state.bg_jobs_tx.try_send(kind.job()) {
+            state.pending_set.remove(&kind);
+            warn!("scheduler: failed to enqueue {:?}: {}", kind, e);
+        }
+    }
+    info!("scheduler: {:?} stopped", kind);
+}
+
+/// 把溢出缓冲区中的作业回灌到队列，队列再次写满时停止；返回剩余条数
+pub fn flush_spill(state: &AppState) -> usize {
+    let mut spill = state.bg_spill.lock().unwrap_or_else(|e| e.into_inner());
+    while let Some(job) = spill.pop_front() {
+        match state.bg_jobs_tx.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(job)) | Err(TrySendError::Closed(job)) => {
+                spill.push_front(job);
+                break;
+            }
+        }
+    }
+    spill.len()
+}
+
+/// 启动溢出缓冲区回灌任务，并在溢出统计变化时输出告警日志
+pub fn spawn_spill_flusher(
+    state: Arc<AppState>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval_ms = state.config.read().await.bg_spill_flush_interval_ms;
+        let mut ticker = tokio::time::interval(Duration::from_millis(interval_ms.max(1)));
+        let mut last = (0, 0, 0);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.changed() => {}
+            }
+            if *shutdown.borrow() || shutdown.has_changed().is_err() {
+                break;
+            }
+
+            let remaining = flush_spill(&state);
+
+            let stats = &state.overflow_stats;
+            let current = (
+                stats.deferred.load(Ordering::Relaxed),
+                stats.inline.load(Ordering::Relaxed),
+                stats.dropped.load(Ordering::Relaxed),
+            );
+            if current != last {
+                warn!(
+                    "bg_jobs overflow: deferred={} inline={} dropped={} spill_remaining={}",
+                    current.0, current.1, current.2, remaining
+                );
+                last = current;
+            }
+        }
+        info!("spill flusher: stopped");
+    })
+}
+
+/// 定期检查目标域名黑名单文件，有修改时重新加载；加载失败保留原黑名单
+pub fn spawn_blocklist_reloader(
+    state: Arc<AppState>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = state
+            .config
+            .read()
+            .await
+            .destination_blocklist_reload_interval;
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.changed() => {}
+            }
+            if *shutdown.borrow() || shutdown.has_changed().is_err() {
+                break;
+            }
+
+            if let Err(e) = state.destination_policy.reload_blocklist() {
+                warn!("blocklist reloader: {}", e);
+            }
+        }
+        info!("blocklist reloader: stopped");
+    })
+}
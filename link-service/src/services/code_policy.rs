@@ -0,0 +1,194 @@
+//! 自定义短码校验规则
+//!
+//! 依次校验长度、字符集、保留词和不雅词；与已有短码"形近"的检查需要查库，由调用方用 [`skeleton`] 完成。
+//! 保留词和不雅词都按骨架比较，`adm1n`、`L0GIN` 这类变体同样会被拒绝。
+use axum::http::StatusCode;
+use std::collections::HashSet;
+use tracing::warn;
+
+use crate::config::AppConfig;
+
+/// 与路由冲突的路径，始终保留
+const BUILTIN_RESERVED: &[&str] = &["s", "shorten", "links", "delete", "stats", "api"];
+
+/// 形近字符归一：先转小写，再把易混字符映射到同一字符，最后合并易混的字符组合
+///
+/// 迁移脚本中回填 `links.code_skeleton` 的 SQL 与此保持一致，修改时需同步。
+pub fn skeleton(code: &str) -> String {
+    let mapped: String = code
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '2' => 'z',
+            '5' => 's',
+            '8' => 'b',
+            c => c,
+        })
+        .collect();
+    mapped.replace("rn", "m").replace("vv", "w")
+}
+
+pub struct CodePolicy {
+    alphabet: HashSet<char>,
+    alphabet_display: String,
+    min_len: usize,
+    max_len: usize,
+    /// 保留词骨架
+    reserved: HashSet<String>,
+    /// 不雅词骨架，按子串匹配
+    profanity: Vec<String>,
+}
+
+impl CodePolicy {
+    /// 按配置创建，读取保留词和不雅词文件；路径为空表示不启用
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        let min_len = config.custom_code_min_len;
+        let max_len = config.custom_code_max_len;
+        // links.short_code 为 VARCHAR(64)
+        if min_len == 0 || min_len > max_len || max_len > 64 {
+            return Err("custom code length must satisfy 1 <= min <= max <= 64".into());
+        }
+
+        let mut reserved: HashSet<String> = BUILTIN_RESERVED.iter().map(|w| skeleton(w)).collect();
+        reserved.extend(Self::load_words(&config.short_code_reserved_file)?);
+        let profanity = Self::load_words(&config.short_code_profanity_file)?;
+
+        Ok(Self::new(
+            &config.custom_code_alphabet,
+            min_len,
+            max_len,
+            reserved,
+            profanity,
+        ))
+    }
+
+    fn new(
+        alphabet: &str,
+        min_len: usize,
+        max_len: usize,
+        reserved: HashSet<String>,
+        profanity: Vec<String>,
+    ) -> Self {
+        Self {
+            alphabet: alphabet.chars().collect(),
+            alphabet_display: alphabet.to_string(),
+            min_len,
+            max_len,
+            reserved,
+            profanity,
+        }
+    }
+
+    /// 读取词表：每行一个词，忽略空行和 `#` 开头的注释，返回骨架
+    fn load_words(path: &str) -> Result<Vec<String>, String> {
+        if path.is_empty() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read word list {}: {}", path, e))?;
+
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(skeleton)
+            .collect())
+    }
+
+    /// 校验用户自定义短码，失败返回 400
+    pub fn check(&self, code: &str) -> Result<(), (StatusCode, String)> {
+        let len = code.chars().count();
+        if len < self.min_len || len > self.max_len {
+            warn!("code_policy: 短码长度越界: short_code={:?}", code);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Short code must be between {} and {} characters",
+                    self.min_len, self.max_len
+                ),
+            ));
+        }
+
+        if !code.chars().all(|c| self.alphabet.contains(&c)) {
+            warn!("code_policy: 短码包含非法字符: short_code={:?}", code);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Short code may only contain characters from: {}",
+                    self.alphabet_display
+                ),
+            ));
+        }
+
+        if self.is_reserved(code) {
+            warn!("code_policy: 短码为保留词: short_code={}", code);
+            return Err((StatusCode::BAD_REQUEST, "Short code is reserved".into()));
+        }
+
+        if self.is_profane(code) {
+            warn!("code_policy: 短码包含不雅词: short_code={}", code);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Short code contains inappropriate words".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 自动生成的短码是否可用（只排除保留词和不雅词）
+    pub fn allows_generated(&self, code: &str) -> bool {
+        !self.is_reserved(code) && !self.is_profane(code)
+    }
+
+    fn is_reserved(&self, code: &str) -> bool {
+        self.reserved.contains(&skeleton(code))
+    }
+
+    fn is_profane(&self, code: &str) -> bool {
+        let sk = skeleton(code);
+        self.profanity.iter().any(|word| sk.contains(word.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> CodePolicy {
+        let mut reserved: HashSet<String> = BUILTIN_RESERVED.iter().map(|w| skeleton(w)).collect();
+        reserved.insert(skeleton("admin"));
+        CodePolicy::new(
+            "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_",
+            3,
+            10,
+            reserved,
+            vec![skeleton("damn")],
+        )
+    }
+
+    #[test]
+    fn test_skeleton() {
+        assert_eq!(skeleton("Log1n"), skeleton("login"));
+        assert_eq!(skeleton("B00K"), "book");
+        assert_eq!(skeleton("rnail"), skeleton("mail"));
+        assert_ne!(skeleton("abc"), skeleton("abd"));
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = policy();
+        assert!(policy.check("my-link_1").is_ok());
+        assert!(policy.check("ab").is_err());
+        assert!(policy.check("abcdefghijk").is_err());
+        assert!(policy.check("a/b/c").is_err());
+        assert!(policy.check("短码短码").is_err());
+        assert!(policy.check("ADM1N").is_err());
+        assert!(policy.check("stats").is_err());
+        assert!(policy.check("xxDAMNxx").is_err());
+        assert!(!policy.allows_generated("adm1n"));
+        assert!(policy.allows_generated("x7Kq2"));
+    }
+}
@@ -0,0 +1,257 @@
+//! 目标地址安全策略
+//!
+//! 创建和编辑（含回滚）短链时对目标地址的规范形式依次检查：
+//! - 协议必须在允许列表中（默认只允许 http / https），拒绝 `javascript:`、`data:` 等
+//! - 主机不能是私有、回环、链路本地等内网 IP 字面量，也不能是 `localhost`
+//! - 主机及其上级域名不能在域名黑名单文件中；黑名单文件修改后由后台任务定期重新加载
+//! - 主机不能是本服务的短链域名，避免短链互相跳转形成循环
+use axum::http::StatusCode;
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, Ipv6Addr},
+    sync::RwLock,
+    time::SystemTime,
+};
+use tracing::{info, warn};
+use url::{Host, Url};
+
+use crate::config::AppConfig;
+
+/// 域名黑名单及其文件修改时间
+#[derive(Default)]
+struct Blocklist {
+    domains: HashSet<String>,
+    modified: Option<SystemTime>,
+}
+
+pub struct DestinationPolicy {
+    allowed_schemes: HashSet<String>,
+    /// 本服务的短链主机名
+    self_hosts: HashSet<String>,
+    blocklist_file: String,
+    blocklist: RwLock<Blocklist>,
+}
+
+impl DestinationPolicy {
+    /// 按配置创建并加载域名黑名单；黑名单路径为空表示不启用
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        let allowed_schemes: HashSet<String> = config
+            .destination_allowed_schemes
+            .split(',')
+            .map(|s| s.trim().to_ascii_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if allowed_schemes.is_empty() {
+            return Err("destination_allowed_schemes must not be empty".into());
+        }
+
+        // 服务监听地址和对外的短链域名都算作本服务主机
+        let mut self_hosts: HashSet<String> = config
+            .shortlink_self_hosts
+            .split(',')
+            .filter_map(parse_domain)
+            .collect();
+        self_hosts.extend(parse_host_of(&config.addr));
+
+        let policy = Self::new(
+            allowed_schemes,
+            self_hosts,
+            config.destination_blocklist_file.clone(),
+        );
+        policy.reload_blocklist()?;
+        Ok(policy)
+    }
+
+    fn new(
+        allowed_schemes: HashSet<String>,
+        self_hosts: HashSet<String>,
+        blocklist_file: String,
+    ) -> Self {
+        Self {
+            allowed_schemes,
+            self_hosts,
+            blocklist_file,
+            blocklist: RwLock::new(Blocklist::default()),
+        }
+    }
+
+    /// 黑名单文件有变化时重新加载，返回是否重新加载；读取失败时保留原黑名单
+    pub fn reload_blocklist(&self) -> Result<bool, String> {
+        if self.blocklist_file.is_empty() {
+            return Ok(false);
+        }
+
+        let path = &self.blocklist_file;
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| format!("failed to stat blocklist {}: {}", path, e))?;
+        if self.blocklist.read().unwrap().modified == Some(modified) {
+            return Ok(false);
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read blocklist {}: {}", path, e))?;
+        let domains: HashSet<String> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(parse_domain)
+            .collect();
+
+        info!(
+            "destination_policy: 域名黑名单已加载: file={}, domains={}",
+            path,
+            domains.len()
+        );
+        *self.blocklist.write().unwrap() = Blocklist {
+            domains,
+            modified: Some(modified),
+        };
+        Ok(true)
+    }
+
+    /// 检查目标地址（规范形式），不允许时返回 400
+    pub fn check(&self, canonical_url: &str) -> Result<(), (StatusCode, String)> {
+        let url = Url::parse(canonical_url).map_err(|e| {
+            warn!(
+                "destination_policy: URL 解析失败: url={}, err={}",
+                canonical_url, e
+            );
+            (StatusCode::BAD_REQUEST, "Invalid URL".into())
+        })?;
+
+        if !self.allowed_schemes.contains(url.scheme()) {
+            warn!("destination_policy: 协议不允许: url={}", canonical_url);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("URL scheme '{}' is not allowed", url.scheme()),
+            ));
+        }
+
+        let blocked_ip = match url.host() {
+            Some(Host::Ipv4(ip)) => is_internal_v4(ip),
+            Some(Host::Ipv6(ip)) => is_internal_v6(ip),
+            Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+            None => {
+                warn!("destination_policy: 缺少主机: url={}", canonical_url);
+                return Err((StatusCode::BAD_REQUEST, "URL must have a host".into()));
+            }
+        };
+        if blocked_ip {
+            warn!("destination_policy: 内网地址: url={}", canonical_url);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "URL must not point to a private or loopback address".into(),
+            ));
+        }
+
+        let host = url.host_str().unwrap_or_default().trim_end_matches('.');
+        if self.self_hosts.contains(host) {
+            warn!("destination_policy: 指向本服务短链: url={}", canonical_url);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "URL must not point to this short link service".into(),
+            ));
+        }
+
+        let Some(Host::Domain(domain)) = url.host() else {
+            return Ok(());
+        };
+        let domain = domain.trim_end_matches('.');
+
+        // 依次检查主机及其上级域名
+        let blocklist = self.blocklist.read().unwrap();
+        let blocked = std::iter::successors(Some(domain), |d| d.split_once('.').map(|(_, p)| p))
+            .any(|d| blocklist.domains.contains(d));
+        if blocked {
+            warn!("destination_policy: 域名在黑名单中: url={}", canonical_url);
+            return Err((StatusCode::BAD_REQUEST, "URL domain is blocked".into()));
+        }
+
+        Ok(())
+    }
+}
+
+/// 解析域名（支持国际化域名），返回小写 punycode 形式
+fn parse_domain(s: &str) -> Option<String> {
+    match Host::parse(s.trim().trim_end_matches('.')) {
+        Ok(Host::Domain(domain)) if !domain.is_empty() => Some(domain),
+        Ok(Host::Ipv4(ip)) => Some(ip.to_string()),
+        _ => None,
+    }
+}
+
+/// 取 `host:port` 或完整 URL 中的主机名
+fn parse_host_of(addr: &str) -> Option<String> {
+    let url = if addr.contains("://") {
+        Url::parse(addr)
+    } else {
+        Url::parse(&format!("http://{}", addr))
+    };
+    url.ok()?.host_str().and_then(parse_domain)
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // 100.64.0.0/10 运营商级 NAT
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_internal_v4(v4);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // fc00::/7 唯一本地地址
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 链路本地地址
+        || (first & 0xffc0) == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> DestinationPolicy {
+        let policy = DestinationPolicy::new(
+            ["http".to_string(), "https".to_string()].into(),
+            ["sho.rt".to_string()].into(),
+            String::new(),
+        );
+        policy.blocklist.write().unwrap().domains = ["evil.example".to_string()].into();
+        policy
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = policy();
+        assert!(policy.check("https://example.com/a").is_ok());
+        assert!(policy.check("https://8.8.8.8/").is_ok());
+        assert!(policy.check("javascript:alert(1)").is_err());
+        assert!(policy.check("data:text/html,hi").is_err());
+        assert!(policy.check("ftp://example.com/").is_err());
+        assert!(policy.check("http://127.0.0.1:8080/").is_err());
+        assert!(policy.check("http://10.1.2.3/").is_err());
+        assert!(policy.check("http://[::1]/").is_err());
+        assert!(policy.check("http://[::ffff:192.168.0.1]/").is_err());
+        assert!(policy.check("http://localhost/").is_err());
+        assert!(policy.check("https://sho.rt/abc").is_err());
+        assert!(policy.check("https://SHO.RT./abc").is_err());
+        assert!(policy.check("https://evil.example/").is_err());
+        assert!(policy.check("https://a.b.evil.example/").is_err());
+        assert!(policy.check("https://notevil.example/").is_ok());
+    }
+
+    #[test]
+    fn test_parse_host_of() {
+        assert_eq!(parse_host_of("0.0.0.0:3000").as_deref(), Some("0.0.0.0"));
+        assert_eq!(parse_host_of("https://Sho.RT/s").as_deref(), Some("sho.rt"));
+    }
+}
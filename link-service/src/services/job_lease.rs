@@ -0,0 +1,193 @@
+//! 基于 Redis 的定时任务租约
+//!
+//! 多个 link-service 副本共享同一份 Redis，同一类定时任务同一时刻只允许一个副本执行。
+//! 租约值为 `{instance_id}:{fence}`，`fence` 每次成功抢占时单调递增，
+//! 续约、校验和释放都要求值完全一致，过期后被其他副本抢走的旧持有者无法再续约或误删新租约。
+//!
+//! 旧持有者可能在租约过期后才恢复执行（如长时间 GC 或网络分区），因此非幂等的作业（点击量同步、
+//! 过期短链清理）在写入 MySQL 的事务内用 [`Fence::check`] 校验 fence，被接管后提交会被拒绝；
+//! 访问日志按 Stream 条目 ID 幂等写入，重复写入无副作用，不做校验。
+use axum::http::StatusCode;
+use deadpool_redis::Connection;
+use redis::Script;
+use sqlx::{MySql, Transaction};
+use tracing::warn;
+
+use crate::state::ScheduledJobKind;
+
+/// 租约的围栏令牌，传给作业在写入前校验
+#[derive(Debug, Clone, Copy)]
+pub struct Fence {
+    job: ScheduledJobKind,
+    token: u64,
+}
+
+impl Fence {
+    /// 在写入事务内登记并复核 fence：表中已有更大的 fence 说明租约已被其他副本接管，返回 409
+    ///
+    /// 登记时对 `job_fences` 行加锁，持有期间新持有者的校验会等待本事务结束。
+    pub async fn check(&self, tx: &mut Transaction<'_, MySql>) -> Result<(), (StatusCode, String)> {
+        let job = self.job.as_str();
+        sqlx::query(
+            "INSERT INTO job_fences (job, fence) VALUES (?, ?) \
+             ON DUPLICATE KEY UPDATE fence = GREATEST(fence, VALUES(fence))",
+        )
+        .bind(job)
+        .bind(self.token)
+        .execute(tx.as_mut())
+        .await
+        .map_err(|e| {
+            warn!("job_lease: fence upsert error: job={}, err={}", job, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB update error: {}", e),
+            )
+        })?;
+
+        let (current,): (u64,) = sqlx::query_as("SELECT fence FROM job_fences WHERE job = ?")
+            .bind(job)
+            .fetch_one(tx.as_mut())
+            .await
+            .map_err(|e| {
+                warn!("job_lease: fence select error: job={}, err={}", job, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("DB select error: {}", e),
+                )
+            })?;
+
+        if current != self.token {
+            warn!(
+                "job_lease: stale fence: job={}, fence={}, current={}",
+                job, self.token, current
+            );
+            return Err((StatusCode::CONFLICT, "Job lease lost".into()));
+        }
+        Ok(())
+    }
+}
+
+pub struct JobLease {
+    kind: ScheduledJobKind,
+    key: String,
+    token: String,
+    /// 围栏令牌，每次抢占成功递增
+    pub fence: u64,
+    /// 租约有效期（毫秒）
+    pub ttl_ms: u64,
+}
+
+impl JobLease {
+    /// 尝试抢占租约；已被其他副本持有时返回 `None`
+    pub async fn try_acquire(
+        conn: &mut Connection,
+        kind: ScheduledJobKind,
+        instance_id: &str,
+        ttl_ms: u64,
+    ) -> Result<Option<JobLease>, (StatusCode, String)> {
+        let key = format!("job_lease:{}", kind.as_str());
+        let fence_key = format!("job_lease_fence:{}", kind.as_str());
+
+        // 租约不存在时递增围栏令牌并写入租约，整个过程原子执行
+        let script = Script::new(
+            r#"
+            if redis.call('EXISTS', KEYS[1]) == 1 then
+                return 0
+            end
+            local fence = redis.call('INCR', KEYS[2])
+            redis.call('SET', KEYS[1], ARGV[1] .. ':' .. fence, 'PX', ARGV[2])
+            return fence
+        "#,
+        );
+
+        let fence: u64 = script
+            .key(&key)
+            .key(&fence_key)
+            .arg(instance_id)
+            .arg(ttl_ms)
+            .invoke_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("job_lease: acquire error: key={}, err={}", key, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis error: {}", e),
+                )
+            })?;
+
+        if fence == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(JobLease {
+            token: format!("{}:{}", instance_id, fence),
+            kind,
+            key,
+            fence,
+            ttl_ms,
+        }))
+    }
+
+    /// 本次租约的围栏令牌
+    pub fn fencing(&self) -> Fence {
+        Fence {
+            job: self.kind,
+            token: self.fence,
+        }
+    }
+
+    /// 续约；返回 `false` 表示租约已过期或已被其他副本持有
+    pub async fn renew(&self, conn: &mut Connection) -> Result<bool, (StatusCode, String)> {
+        let script = Script::new(
+            r#"
+            if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+            end
+            return 0
+        "#,
+        );
+
+        let renewed: i64 = script
+            .key(&self.key)
+            .arg(&self.token)
+            .arg(self.ttl_ms)
+            .invoke_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("job_lease: renew error: key={}, err={}", self.key, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis error: {}", e),
+                )
+            })?;
+
+        Ok(renewed == 1)
+    }
+
+    /// 释放租约，只删除自己持有的租约
+    pub async fn release(&self, conn: &mut Connection) -> Result<(), (StatusCode, String)> {
+        let script = Script::new(
+            r#"
+            if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('DEL', KEYS[1])
+            end
+            return 0
+        "#,
+        );
+
+        let _: i64 = script
+            .key(&self.key)
+            .arg(&self.token)
+            .invoke_async(conn)
+            .await
+            .map_err(|e| {
+                warn!("job_lease: release error: key={}, err={}", self.key, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Redis error: {}", e),
+                )
+            })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! 密码保护短链
+//!
+//! 密码用 `common::password`（与 user-service 相同的 argon2 实现）哈希后存储；解锁成功后签发绑定短码的短期 JWT，
+//! 写入名为 `link_unlock_{code}` 的 HttpOnly Cookie，有效期内再次访问无需输入密码。
+use axum::http::StatusCode;
+use common::password;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// 解锁凭证的 `aud`，与用户登录 JWT 区分
+const UNLOCK_AUDIENCE: &str = "link_unlock";
+
+#[derive(Serialize, Deserialize)]
+struct UnlockClaims {
+    /// 短码
+    sub: String,
+    aud: String,
+    /// 过期时间(Unix 秒)
+    exp: i64,
+}
+
+/// 解锁 Cookie 名
+pub fn unlock_cookie_name(short_code: &str) -> String {
+    format!("link_unlock_{}", short_code)
+}
+
+/// 生成密码哈希（在阻塞线程池中执行）
+pub async fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
+    password::hash_password(password)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// 校验密码（在阻塞线程池中执行）
+pub async fn verify_password(
+    password: &str,
+    password_hash: &str,
+) -> Result<bool, (StatusCode, String)> {
+    password::verify_password(password, password_hash)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// 签发解锁凭证
+pub fn issue_unlock_token(
+    secret: &str,
+    short_code: &str,
+    ttl: i64,
+) -> Result<String, (StatusCode, String)> {
+    let claims = UnlockClaims {
+        sub: short_code.to_string(),
+        aud: UNLOCK_AUDIENCE.into(),
+        exp: chrono::Utc::now().timestamp() + ttl,
+    };
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .map_err(|e| {
+        warn!(
+            "issue_unlock_token: JWT encode error: short_code={}, err={}",
+            short_code, e
+        );
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("JWT encode error: {}", e),
+        )
+    })
+}
+
+/// 校验解锁凭证：签名有效、未过期且属于该短码
+pub fn verify_unlock_token(secret: &str, short_code: &str, token: &str) -> bool {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[UNLOCK_AUDIENCE]);
+    decode::<UnlockClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .is_ok_and(|data| data.claims.sub == short_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_password_and_token() {
+        let hash = hash_password("s3cret").await.unwrap();
+        assert!(verify_password("s3cret", &hash).await.unwrap());
+        assert!(!verify_password("wrong", &hash).await.unwrap());
+
+        let token = issue_unlock_token("secret", "abc123", 60).unwrap();
+        assert!(verify_unlock_token("secret", "abc123", &token));
+        assert!(!verify_unlock_token("secret", "other", &token));
+        assert!(!verify_unlock_token("another", "abc123", &token));
+
+        let expired = issue_unlock_token("secret", "abc123", -120).unwrap();
+        assert!(!verify_unlock_token("secret", "abc123", &expired));
+    }
+}
@@ -0,0 +1,302 @@
+//! 短码生成策略
+//!
+//! - `sequential`：id 直接按字母表做进制转换，短码随 id 递增，可被枚举
+//! - `random`：系统随机数生成定长短码，与 id 无关
+//! - `feistel`：用 Feistel 网络对 id 做可逆置换后再编码，短码定长且不可预测，不同 id 的首个候选不会撞码
+use rand::{Rng, rngs::OsRng};
+
+use crate::config::AppConfig;
+
+/// 短码生成器
+pub trait ShortCodeGenerator: Send + Sync {
+    /// 为 `id` 生成第 `attempt` 个候选短码；前一个候选已被占用时调用方递增 `attempt` 重试
+    fn generate(&self, id: u64, attempt: u64) -> String;
+}
+
+/// 按配置创建短码生成器，配置非法时返回错误信息
+pub fn from_config(config: &AppConfig) -> Result<Box<dyn ShortCodeGenerator>, String> {
+    let alphabet = Alphabet::new(&config.short_code_alphabet)?;
+    let length = config.short_code_length;
+    if length == 0 || alphabet.domain(length).is_none() {
+        return Err(format!(
+            "short_code_length must be between 1 and {}",
+            alphabet.max_length()
+        ));
+    }
+
+    match config.short_code_strategy.as_str() {
+        "sequential" => Ok(Box::new(SequentialGenerator { alphabet, length })),
+        "random" => Ok(Box::new(RandomGenerator { alphabet, length })),
+        "feistel" => {
+            if config.short_code_secret.is_empty() {
+                return Err("short_code_secret is required for feistel strategy".into());
+            }
+            Ok(Box::new(FeistelGenerator::new(
+                alphabet,
+                length,
+                &config.short_code_secret,
+            )))
+        }
+        other => Err(format!("unknown short_code_strategy: {}", other)),
+    }
+}
+
+/// 短码字母表
+#[derive(Debug, Clone)]
+struct Alphabet(Vec<u8>);
+
+impl Alphabet {
+    /// 置换的取值范围上限，保证两半拼起来不超过 64 位
+    const MAX_DOMAIN: u64 = 1 << 62;
+
+    fn new(chars: &str) -> Result<Self, String> {
+        let bytes = chars.as_bytes().to_vec();
+        if bytes.len() < 2 {
+            return Err("short_code_alphabet needs at least 2 characters".into());
+        }
+        if !bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_')
+        {
+            return Err("short_code_alphabet may only contain [0-9A-Za-z_-]".into());
+        }
+        let mut sorted = bytes.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != bytes.len() {
+            return Err("short_code_alphabet contains duplicate characters".into());
+        }
+        Ok(Self(bytes))
+    }
+
+    fn base(&self) -> u64 {
+        self.0.len() as u64
+    }
+
+    /// `length` 位短码的取值个数，超出上限返回 `None`
+    fn domain(&self, length: usize) -> Option<u64> {
+        self.base()
+            .checked_pow(length as u32)
+            .filter(|n| *n <= Self::MAX_DOMAIN)
+    }
+
+    fn max_length(&self) -> usize {
+        (1..)
+            .take_while(|&len| self.domain(len).is_some())
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// 进制转换，不足 `min_length` 位时左侧补首字符
+    fn encode(&self, mut n: u64, min_length: usize) -> String {
+        let base = self.base();
+        let mut buf = Vec::with_capacity(min_length);
+        while n > 0 {
+            buf.push(self.0[(n % base) as usize]);
+            n /= base;
+        }
+        while buf.len() < min_length.max(1) {
+            buf.push(self.0[0]);
+        }
+        buf.reverse();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// 顺序短码：`id + attempt` 直接编码
+pub struct SequentialGenerator {
+    alphabet: Alphabet,
+    length: usize,
+}
+
+impl ShortCodeGenerator for SequentialGenerator {
+    fn generate(&self, id: u64, attempt: u64) -> String {
+        self.alphabet.encode(id + attempt, self.length)
+    }
+}
+
+/// 随机短码：每个字符独立从字母表中均匀选取
+pub struct RandomGenerator {
+    alphabet: Alphabet,
+    length: usize,
+}
+
+impl ShortCodeGenerator for RandomGenerator {
+    fn generate(&self, _id: u64, _attempt: u64) -> String {
+        let mut rng = OsRng;
+        let buf: Vec<u8> = (0..self.length)
+            .map(|_| self.alphabet.0[rng.gen_range(0..self.alphabet.0.len())])
+            .collect();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// Feistel 置换短码
+///
+/// 在 `[0, base^length)` 上做平衡 Feistel 置换，超出范围的结果继续置换直到落回范围内（cycle walking），
+/// 因此是该范围上的双射，不同 id 的首个候选（`attempt = 0`）互不相同。id 超出范围时自动增加位数。
+///
+/// 首个候选只会被自定义短码占用；重试时不取相邻 id（那是下一个 id 的短码，必然在之后撞上），
+/// 而是把 `attempt` 混入轮密钥，对同一个 id 做另一个置换。重试候选仍可能恰好等于其他 id 的首个候选，
+/// 那个 id 写入时会冲突并重试，概率与随机短码相当。
+pub struct FeistelGenerator {
+    alphabet: Alphabet,
+    length: usize,
+    keys: [u64; Self::ROUNDS],
+}
+
+impl FeistelGenerator {
+    const ROUNDS: usize = 4;
+
+    fn new(alphabet: Alphabet, length: usize, secret: &str) -> Self {
+        // FNV-1a 得到种子，再用 splitmix64 派生每轮密钥；不依赖标准库哈希，保证跨版本稳定
+        let mut seed = 0xcbf2_9ce4_8422_2325_u64;
+        for b in secret.as_bytes() {
+            seed ^= *b as u64;
+            seed = seed.wrapping_mul(0x0100_0000_01b3);
+        }
+        let mut keys = [0; Self::ROUNDS];
+        for key in keys.iter_mut() {
+            seed = splitmix64(seed);
+            *key = seed;
+        }
+        Self {
+            alphabet,
+            length,
+            keys,
+        }
+    }
+
+    /// 在 `[0, domain)` 上置换 `x`，`tweak` 混入每轮密钥，不同 `tweak` 得到不同的置换
+    fn permute(&self, x: u64, domain: u64, tweak: u64) -> u64 {
+        let bits = (64 - (domain - 1).leading_zeros()).max(2);
+        let half = bits.div_ceil(2);
+        let mask = (1_u64 << half) - 1;
+
+        let mut y = x;
+        loop {
+            let (mut l, mut r) = (y >> half, y & mask);
+            for key in &self.keys {
+                let f = splitmix64(r ^ key ^ tweak) & mask;
+                (l, r) = (r, l ^ f);
+            }
+            y = (l << half) | r;
+            if y < domain {
+                return y;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn unpermute(&self, y: u64, domain: u64, tweak: u64) -> u64 {
+        let bits = (64 - (domain - 1).leading_zeros()).max(2);
+        let half = bits.div_ceil(2);
+        let mask = (1_u64 << half) - 1;
+
+        let mut x = y;
+        loop {
+            let (mut l, mut r) = (x >> half, x & mask);
+            for key in self.keys.iter().rev() {
+                let f = splitmix64(l ^ key ^ tweak) & mask;
+                (l, r) = (r ^ f, l);
+            }
+            x = (l << half) | r;
+            if x < domain {
+                return x;
+            }
+        }
+    }
+}
+
+impl ShortCodeGenerator for FeistelGenerator {
+    fn generate(&self, id: u64, attempt: u64) -> String {
+        let tweak = if attempt == 0 { 0 } else { splitmix64(attempt) };
+        let mut length = self.length;
+        while let Some(domain) = self.alphabet.domain(length) {
+            if id < domain {
+                return self
+                    .alphabet
+                    .encode(self.permute(id, domain, tweak), length);
+            }
+            length += 1;
+        }
+        // 超出置换范围（约 2^62）时退化为顺序编码
+        self.alphabet.encode(id + attempt, self.length)
+    }
+}
+
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const BASE62: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    #[test]
+    fn test_sequential() {
+        let generator = SequentialGenerator {
+            alphabet: Alphabet::new(BASE62).unwrap(),
+            length: 1,
+        };
+        assert_eq!(generator.generate(1, 0), "1");
+        assert_eq!(generator.generate(61, 1), "10");
+        assert_eq!(generator.generate(62 * 62, 0), "100");
+
+        let padded = SequentialGenerator {
+            alphabet: Alphabet::new(BASE62).unwrap(),
+            length: 4,
+        };
+        assert_eq!(padded.generate(62, 0), "0010");
+    }
+
+    #[test]
+    fn test_random() {
+        let generator = RandomGenerator {
+            alphabet: Alphabet::new("abc").unwrap(),
+            length: 8,
+        };
+        let code = generator.generate(1, 0);
+        assert_eq!(code.len(), 8);
+        assert!(code.bytes().all(|b| b"abc".contains(&b)));
+    }
+
+    #[test]
+    fn test_feistel_is_permutation() {
+        let generator = FeistelGenerator::new(Alphabet::new("0123456789").unwrap(), 3, "secret");
+        let codes: HashSet<String> = (0..1000).map(|id| generator.generate(id, 0)).collect();
+        assert_eq!(codes.len(), 1000);
+        assert!(codes.iter().all(|c| c.len() == 3));
+
+        for id in [0, 1, 999] {
+            for tweak in [0, splitmix64(1)] {
+                let y = generator.permute(id, 1000, tweak);
+                assert_eq!(generator.unpermute(y, 1000, tweak), id);
+            }
+        }
+
+        // 重试候选不是相邻 id 的首个候选
+        let retries = (0..1000)
+            .filter(|&id| generator.generate(id, 1) == generator.generate(id + 1, 0))
+            .count();
+        assert!(retries < 10);
+        assert_ne!(generator.generate(5, 1), generator.generate(5, 0));
+
+        // 超出 3 位范围时自动加长
+        assert_eq!(generator.generate(1000, 0).len(), 4);
+    }
+
+    #[test]
+    fn test_alphabet_validation() {
+        assert!(Alphabet::new("a").is_err());
+        assert!(Alphabet::new("aab").is_err());
+        assert!(Alphabet::new("ab/").is_err());
+        assert_eq!(Alphabet::new("01").unwrap().max_length(), 62);
+    }
+}
@@ -1,41 +1,260 @@
 use crate::services::background_jobs::BackgroundJob;
+use crate::services::code_policy::skeleton;
+use crate::services::link_password;
 use crate::{
     handlers::LinkQuery,
-    models::{Link, LinkView},
+    models::{Link, LinkInsert, LinkRevisionView, LinkView, is_activated},
     state::AppState,
 };
 use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use common::rate_limiter::{is_limited, rate_limit};
 use deadpool_redis::Connection;
+use sqlx::{MySql, Transaction};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+use tokio::sync::mpsc::error::TrySendError;
 use tracing::warn;
 
 pub struct ShortlinkService;
 
+/// 每轮为每个 id 生成的候选短码数
+const CODE_CANDIDATES_PER_ROUND: u64 = 4;
+/// 预检候选短码的最大轮数
+const CODE_MAX_ROUNDS: u64 = 25;
+/// 预检通过但写入时被并发抢占的最大重试次数
+const CODE_WRITE_RETRIES: usize = 3;
+
+/// 待创建的一条短链
+pub struct NewShortlink {
+    pub long_url: String,
+    /// 目标地址的规范形式
+    pub canonical_url: String,
+    pub short_code: Option<String>,
+    /// 过期时间，`None` 为永久有效
+    pub expire_at: Option<DateTime<Utc>>,
+    /// 生效时间，`None` 为创建即生效
+    pub activate_at: Option<DateTime<Utc>>,
+    /// 闲置天数，超过该天数没有点击即过期，`None` 为不按闲置过期
+    pub inactivity_days: Option<u16>,
+    /// 跳转次数上限，`None` 为不限
+    pub max_clicks: Option<u32>,
+    /// 访问密码（明文），`None` 为不需要密码
+    pub password: Option<String>,
+    /// 过期后宽限期内访问时引导到的落地页，`None` 为不跳转
+    pub expired_landing_url: Option<String>,
+}
+
+impl NewShortlink {
+    /// 每次跳转都需回源 MySQL 检查（限次或有密码），不写跳转缓存
+    fn skip_cache(&self) -> bool {
+        self.max_clicks.is_some() || self.password.is_some()
+    }
+
+    /// 跳转缓存时长：剩余有效期和闲置天数中较短者，不超过 `redis_max_ttl`；
+    /// 永久短链按 `redis_max_ttl` 缓存
+    fn cache_ttl(&self, redis_max_ttl: i64) -> i64 {
+        let ttl = self.expire_at.map_or(redis_max_ttl, |t| {
+            (t - Utc::now()).num_seconds().min(redis_max_ttl)
+        });
+        self.inactivity_days
+            .map_or(ttl, |days| ttl.min(i64::from(days) * 86400))
+    }
+}
+
+/// 创建结果
+pub struct CreatedShortlink {
+    pub short_url: String,
+    /// 是否复用了已有短链
+    pub reused: bool,
+    /// 过期时间，`None` 为永久有效；复用时为已有短链的过期时间
+    pub expire_at: Option<DateTime<Utc>>,
+}
+
+/// 跳转结果
+pub enum RedirectOutcome {
+    /// 跳转到目标地址
+    Redirect(String),
+    /// 需要输入密码解锁
+    PasswordRequired,
+    /// 已过期但仍在宽限期内，返回 410 并引导到所有者设置的落地页
+    Expired { landing_url: Option<String> },
+}
+
+/// 过期时间 `expired_at` 仍在宽限期 `grace_period`（秒）内时返回 [`RedirectOutcome::Expired`]，
+/// 否则返回 404
+fn expired_outcome(
+    expired_at: DateTime<Utc>,
+    grace_period: i64,
+    landing_url: Option<String>,
+) -> Result<RedirectOutcome, (StatusCode, String)> {
+    if Utc::now() < expired_at + chrono::Duration::seconds(grace_period) {
+        Ok(RedirectOutcome::Expired { landing_url })
+    } else {
+        Err((StatusCode::NOT_FOUND, "Link expired".into()))
+    }
+}
+
 impl ShortlinkService {
-    /// Base62 编码函数
-    fn encode_base62(mut id: u64) -> String {
-        const BASE62_CHARS: &[u8] =
-            b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
-        let mut buf = Vec::new();
-        while id > 0 {
-            buf.push(BASE62_CHARS[(id % 62) as usize]);
-            id /= 62;
-        }
-        if buf.is_empty() {
-            buf.push(b'0');
+    /// 为一组 id 分配未被占用的短码，结果与 `ids` 顺序一致
+    ///
+    /// 每轮为尚未分配的 id 各生成 `CODE_CANDIDATES_PER_ROUND` 个候选，一条 SELECT 查出已占用的，
+    /// 再取第一个可用的；`taken` 记录本次已分配（含自定义）的短码，避免批内重复。
+    async fn pick_codes(
+        state: &AppState,
+        tx: &mut Transaction<'_, MySql>,
+        ids: &[u64],
+        taken: &mut HashSet<String>,
+    ) -> Result<Vec<String>, (StatusCode, String)> {
+        let mut codes: Vec<Option<String>> = vec![None; ids.len()];
+
+        for round in 0..CODE_MAX_ROUNDS {
+            let todo: Vec<usize> = (0..ids.len()).filter(|&k| codes[k].is_none()).collect();
+            if todo.is_empty() {
+                break;
+            }
+
+            let candidates: Vec<Vec<String>> = todo
+                .iter()
+                .map(|&k| {
+                    (0..CODE_CANDIDATES_PER_ROUND)
+                        .map(|j| {
+                            state
+                                .code_generator
+                                .generate(ids[k], round * CODE_CANDIDATES_PER_ROUND + j)
+                        })
+                        .collect()
+                })
+                .collect();
+            let flat: Vec<String> = candidates.iter().flatten().cloned().collect();
+            let existing = Link::find_existing_codes(tx, &flat).await?;
+
+            for (&k, candidates) in todo.iter().zip(candidates) {
+                codes[k] = candidates.into_iter().find(|c| {
+                    !existing.contains(c)
+                        && state.code_policy.allows_generated(c)
+                        && taken.insert(c.clone())
+                });
+            }
         }
-        buf.reverse();
-        String::from_utf8(buf).unwrap()
+
+        codes
+            .into_iter()
+            .zip(ids)
+            .map(|(code, id)| {
+                code.ok_or_else(|| {
+                    warn!(
+                        "pick_codes: 候选短码均已被占用，无法生成唯一短码: id={}",
+                        id
+                    );
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Unable to generate unique short code".into(),
+                    )
+                })
+            })
+            .collect()
     }
 
-    /// 创建短链
-    pub async fn create_shortlink(
+    /// 检查自定义短码是否与已有短码相同或形近，结果与 `codes` 顺序一致
+    ///
+    /// 批内互相形近的短码只保留第一个。
+    async fn check_similar_codes(
+        tx: &mut Transaction<'_, MySql>,
+        codes: &[&str],
+    ) -> Result<Vec<Result<(), (StatusCode, String)>>, (StatusCode, String)> {
+        let skeletons: Vec<String> = codes.iter().map(|c| skeleton(c)).collect();
+        let existing = Link::find_similar_codes(tx, &skeletons).await?;
+
+        let mut seen = HashSet::new();
+        Ok(codes
+            .iter()
+            .zip(skeletons)
+            .map(|(code, sk)| {
+                if let Some(other) = existing.get(&sk) {
+                    warn!(
+                        "check_similar_codes: 短码已存在或形近: short_code={}, existing={}",
+                        code, other
+                    );
+                    if other == code {
+                        return Err((StatusCode::BAD_REQUEST, "Short code already exists".into()));
+                    }
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        "Short code is too similar to an existing code".into(),
+                    ));
+                }
+                if !seen.insert(sk) {
+                    warn!(
+                        "check_similar_codes: 批内短码重复或形近: short_code={}",
+                        code
+                    );
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        "Short code is duplicated or too similar within the batch".into(),
+                    ));
+                }
+                Ok(())
+            })
+            .collect())
+    }
+
+    /// 规范化目标地址并按 `destination_policy` 检查，返回规范形式；创建、编辑和回滚都经过这里
+    pub fn resolve_destination(
         state: &AppState,
         long_url: &str,
-        user_short_code: Option<String>,
-        ttl: i64,
-        user_id: u64,
     ) -> Result<String, (StatusCode, String)> {
-        let expire_at = chrono::Utc::now() + chrono::Duration::seconds(ttl);
+        let canonical_url = state.url_canonicalizer.canonicalize(long_url)?;
+        state.destination_policy.check(&canonical_url)?;
+        Ok(canonical_url)
+    }
+
+    /// 创建短链，`expire_at` 为 `None` 时永久有效，`max_clicks` 为 `None` 时不限跳转次数
+    ///
+    /// `link.canonical_url` 为经 [`Self::resolve_destination`] 检查后的规范形式，与原始地址一起存储。
+    /// `reuse_existing` 为 true 且未指定自定义短码、生效时间、次数上限和密码时，若该用户已有原始地址和
+    /// 过期时间都相同、已生效未过期、不限次数且无密码的短链，直接返回该短链，不再新建。
+    /// 限次和有密码的短链不写跳转缓存，每次跳转都回源 MySQL；未到生效时间的短链也不写缓存。
+    pub async fn create_shortlink(
+        state: &AppState,
+        link: NewShortlink,
+        reuse_existing: bool,
+        user_id: u64,
+    ) -> Result<CreatedShortlink, (StatusCode, String)> {
+        let reusable = if reuse_existing
+            && link.short_code.is_none()
+            && link.activate_at.is_none()
+            && link.inactivity_days.is_none()
+            && link.expired_landing_url.is_none()
+            && !link.skip_cache()
+        {
+            Link::find_reusable_link(
+                &state.mysql_pool,
+                user_id,
+                &link.long_url,
+                &link.canonical_url,
+                link.expire_at,
+            )
+            .await?
+        } else {
+            None
+        };
+        if let Some((short_code, expire_at)) = reusable {
+            let base = state.config.read().await.addr.clone();
+            return Ok(CreatedShortlink {
+                short_url: format!("{}/{}", base.trim_end_matches('/'), short_code),
+                reused: true,
+                expire_at: expire_at.map(|t| t.and_utc()),
+            });
+        }
+
+        let password_hash = match link.password.as_deref() {
+            Some(password) => Some(link_password::hash_password(password).await?),
+            None => None,
+        };
         // 开启事务
         let mut tx = state.mysql_pool.begin().await.map_err(|e| {
             warn!("create_shortlink: DB Begin error: {}", e);
@@ -46,15 +265,34 @@ impl ShortlinkService {
         })?;
 
         // 插入长 URL
-        let insert_sql = Link::insert_long_url(&mut tx, long_url, expire_at, user_id).await?;
+        let insert_sql = Link::insert_long_url(
+            &mut tx,
+            &LinkInsert {
+                long_url: &link.long_url,
+                canonical_url: &link.canonical_url,
+                expire_at: link.expire_at,
+                activate_at: link.activate_at,
+                inactivity_days: link.inactivity_days,
+                max_clicks: link.max_clicks,
+                password_hash: password_hash.as_deref(),
+                expired_landing_url: link.expired_landing_url.as_deref(),
+            },
+            user_id,
+        )
+        .await?;
 
         let id = insert_sql.last_insert_id();
         let mut short_code = String::new();
 
-        if let Some(user_short_code) = user_short_code {
-            short_code = user_short_code;
+        if let Some(user_short_code) = &link.short_code {
+            short_code = user_short_code.clone();
+
+            // 拒绝与已有短码相同或形近的短码
+            Self::check_similar_codes(&mut tx, &[short_code.as_str()])
+                .await?
+                .remove(0)?;
 
-            // 直接尝试写入；若违反 UNIQUE 约束， update_short_code 会返回 CONFLICT
+            // 并发创建时仍可能违反 UNIQUE 约束，update_short_code 会返回 CONFLICT
             match Link::update_short_code(&mut tx, id, &short_code).await {
                 Ok(_) => {}
                 Err((StatusCode::CONFLICT, _)) => {
@@ -68,9 +306,12 @@ impl ShortlinkService {
                 Err(e) => return Err(e),
             }
         } else {
-            // 尝试最多 100 次自动生成；遇到唯一键冲突就换一个新码
-            for i in 0..100 {
-                let candidate = Self::encode_base62(id + i as u64);
+            // 预检后写入；并发创建抢占了同一短码时换一个重试
+            let mut taken = HashSet::new();
+            for _ in 0..CODE_WRITE_RETRIES {
+                let candidate = Self::pick_codes(state, &mut tx, &[id], &mut taken)
+                    .await?
+                    .remove(0);
                 match Link::update_short_code(&mut tx, id, &candidate).await {
                     Ok(_) => {
                         short_code = candidate;
@@ -85,7 +326,7 @@ impl ShortlinkService {
 
             if short_code.is_empty() {
                 warn!(
-                    "create_shortlink: 100 次自动生成短码均碰撞，无法生成唯一短码，user_id={}",
+                    "create_shortlink: 写入短码时连续碰撞，无法生成唯一短码，user_id={}",
                     user_id
                 );
                 return Err((
@@ -109,23 +350,230 @@ impl ShortlinkService {
         })?;
 
         // 判断过期时间是否大于设置的redis最大存储时间
-        // 大于则设置为最大存储时间
+        // 大于则设置为最大存储时间，永久短链直接按最大存储时间缓存
         let config = state.config.read().await;
-        let redis_max_ttl = config.redis_max_ttl;
-        let cache_ttl = if ttl > redis_max_ttl {
-            redis_max_ttl
-        } else {
-            ttl
-        };
+        let cache_ttl = link.cache_ttl(config.redis_max_ttl);
 
-        // 将短码和长 URL 存储到 Redis
-        Link::set_shortlink(&mut conn, &short_code, long_url, cache_ttl).await?;
+        // 将短码和长 URL 存储到 Redis（限次、有密码的短链不缓存）
+        if !link.skip_cache() {
+            Link::set_shortlink(
+                &mut conn,
+                &short_code,
+                &link.long_url,
+                cache_ttl,
+                link.activate_at,
+            )
+            .await?;
+        }
 
         // 设置点击量
-        Link::set_click_count(&mut conn, &short_code, ttl).await?;
+        Link::set_click_count(&mut conn, &short_code).await?;
 
         let base = config.addr.clone();
-        Ok(format!("{}/{}", base.trim_end_matches('/'), short_code))
+        Ok(CreatedShortlink {
+            short_url: format!("{}/{}", base.trim_end_matches('/'), short_code),
+            reused: false,
+            expire_at: link.expire_at,
+        })
+    }
+
+    /// 批量写入短码冲突后逐条写入，返回写入失败的 id 及其错误
+    ///
+    /// 自定义短码冲突直接失败；生成的短码冲突时换一个重试，最多 `CODE_WRITE_RETRIES` 次。
+    /// `custom_ids` 为使用自定义短码的 id。成功写入的条目保留在 `assigned` 中（生成的短码可能已更换），
+    /// 失败的条目从中移除。
+    async fn update_codes_one_by_one(
+        state: &AppState,
+        tx: &mut Transaction<'_, MySql>,
+        assigned: &mut Vec<(u64, String)>,
+        custom_ids: &HashSet<u64>,
+        taken: &mut HashSet<String>,
+    ) -> Result<HashMap<u64, (StatusCode, String)>, (StatusCode, String)> {
+        let mut failed = HashMap::new();
+        for (id, code) in assigned.iter_mut() {
+            let generated = !custom_ids.contains(id);
+            let mut written = false;
+            for _ in 0..CODE_WRITE_RETRIES {
+                match Link::update_short_code(tx, *id, code).await {
+                    Ok(()) => {
+                        written = true;
+                        break;
+                    }
+                    Err((StatusCode::CONFLICT, _)) if generated => {
+                        *code = Self::pick_codes(state, tx, &[*id], taken).await?.remove(0);
+                    }
+                    Err((StatusCode::CONFLICT, _)) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            if !written {
+                warn!(
+                    "update_codes_one_by_one: 短码已被占用: id={}, short_code={}",
+                    id, code
+                );
+                let err = if generated {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Unable to generate unique short code".to_string(),
+                    )
+                } else {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        "Short code already exists".to_string(),
+                    )
+                };
+                failed.insert(*id, err);
+            }
+        }
+        assigned.retain(|(id, _)| !failed.contains_key(id));
+
+        Ok(failed)
+    }
+
+    /// 批量创建短链，返回结果与 `items` 顺序一致
+    ///
+    /// 自定义短码先在批内去重并查库预检（含形近检查），冲突的条目单独失败；其余条目在一个事务内
+    /// 多行插入、统一分配短码（单条 `CASE` UPDATE），提交后用一个 pipeline 写缓存。
+    /// 预检后短码被并发请求占用时改为逐条写入，只有冲突的条目失败（生成的短码先换一个重试）。
+    pub async fn create_shortlinks_batch(
+        state: &AppState,
+        items: Vec<NewShortlink>,
+        user_id: u64,
+    ) -> Result<Vec<Result<String, (StatusCode, String)>>, (StatusCode, String)> {
+        let mut results: Vec<Option<Result<String, (StatusCode, String)>>> =
+            (0..items.len()).map(|_| None).collect();
+
+        let mut tx = state.mysql_pool.begin().await.map_err(|e| {
+            warn!("create_shortlinks_batch: DB Begin error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Begin error: {}", e),
+            )
+        })?;
+
+        // 自定义短码：批内重复、已被占用或形近的条目直接失败
+        let custom: Vec<(usize, &str)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| item.short_code.as_deref().map(|code| (idx, code)))
+            .collect();
+        let codes: Vec<&str> = custom.iter().map(|(_, code)| *code).collect();
+        let checked = Self::check_similar_codes(&mut tx, &codes).await?;
+        let mut taken = HashSet::new();
+        for ((idx, code), res) in custom.into_iter().zip(checked) {
+            match res {
+                Ok(()) => {
+                    taken.insert(code.to_string());
+                }
+                Err(e) => results[idx] = Some(Err(e)),
+            }
+        }
+
+        let pending: Vec<usize> = (0..items.len()).filter(|&i| results[i].is_none()).collect();
+        if pending.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
+
+        // 多行插入；密码逐条在阻塞线程池中哈希，不占用 tokio 工作线程，也不会同时占用大量内存
+        let mut password_hashes: Vec<Option<String>> = Vec::with_capacity(pending.len());
+        for &i in &pending {
+            password_hashes.push(match items[i].password.as_deref() {
+                Some(password) => Some(link_password::hash_password(password).await?),
+                None => None,
+            });
+        }
+        let rows: Vec<LinkInsert> = pending
+            .iter()
+            .zip(&password_hashes)
+            .map(|(&i, password_hash)| {
+                let item = &items[i];
+                LinkInsert {
+                    long_url: &item.long_url,
+                    canonical_url: &item.canonical_url,
+                    expire_at: item.expire_at,
+                    activate_at: item.activate_at,
+                    inactivity_days: item.inactivity_days,
+                    max_clicks: item.max_clicks,
+                    password_hash: password_hash.as_deref(),
+                    expired_landing_url: item.expired_landing_url.as_deref(),
+                }
+            })
+            .collect();
+        let ids = Link::insert_long_urls(&mut tx, &rows, user_id).await?;
+
+        // 分配短码：自定义短码直接使用，其余由短码生成器批量生成并预检
+        let mut assigned: Vec<(u64, String)> = Vec::with_capacity(ids.len());
+        let mut auto_ids: Vec<u64> = Vec::new();
+        let mut custom_ids: HashSet<u64> = HashSet::new();
+        for (&i, &id) in pending.iter().zip(&ids) {
+            match &items[i].short_code {
+                Some(code) => {
+                    assigned.push((id, code.clone()));
+                    custom_ids.insert(id);
+                }
+                None => auto_ids.push(id),
+            }
+        }
+
+        let auto_codes = Self::pick_codes(state, &mut tx, &auto_ids, &mut taken).await?;
+        assigned.extend(auto_ids.into_iter().zip(auto_codes));
+
+        let mut failed = HashMap::new();
+        match Link::update_short_codes(&mut tx, &assigned).await {
+            Ok(()) => {}
+            Err((StatusCode::CONFLICT, _)) => {
+                failed = Self::update_codes_one_by_one(
+                    state,
+                    &mut tx,
+                    &mut assigned,
+                    &custom_ids,
+                    &mut taken,
+                )
+                .await?;
+                let failed_ids: Vec<u64> = failed.keys().copied().collect();
+                Link::delete_unassigned_links(&mut tx, &failed_ids).await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        tx.commit().await.map_err(|e| {
+            warn!("create_shortlinks_batch: DB Commit error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Commit error: {}", e),
+            )
+        })?;
+
+        // 写缓存；失败不影响已创建的短链，跳转时会回源 MySQL
+        let config = state.config.read().await;
+        let redis_max_ttl = config.redis_max_ttl;
+        let base = config.addr.trim_end_matches('/').to_string();
+        drop(config);
+
+        let code_by_id: HashMap<u64, String> = assigned.into_iter().collect();
+        let mut cache = Vec::with_capacity(pending.len());
+        for (&i, id) in pending.iter().zip(&ids) {
+            if let Some(e) = failed.remove(id) {
+                results[i] = Some(Err(e));
+                continue;
+            }
+            let code = code_by_id[id].clone();
+            let cacheable = !items[i].skip_cache() && is_activated(items[i].activate_at);
+            let ttl = cacheable.then(|| items[i].cache_ttl(redis_max_ttl));
+            cache.push((code.clone(), items[i].long_url.clone(), ttl));
+            results[i] = Some(Ok(format!("{}/{}", base, code)));
+        }
+
+        match state.redis_pool.get().await {
+            Ok(mut conn) => {
+                if let Err(e) = Link::cache_shortlinks(&mut conn, &cache).await {
+                    warn!("create_shortlinks_batch: 写缓存失败: {:?}", e);
+                }
+            }
+            Err(e) => warn!("create_shortlinks_batch: 获取 Redis 连接失败: {}", e),
+        }
+
+        Ok(results.into_iter().flatten().collect())
     }
 
     /// 增加点击数和访问日志
@@ -136,20 +584,83 @@ impl ShortlinkService {
         ip: String,
         user_agent: String,
         referer: String,
-    ) {
-        Link::log_visit_to_stream(conn, &short_code, &long_url, &ip, &user_agent, &referer).await;
+        counted: bool,
+    ) -> Result<(), (StatusCode, String)> {
+        let logged =
+            Link::log_visit_to_stream(conn, &short_code, &long_url, &ip, &user_agent, &referer)
+                .await;
+
+        if counted {
+            return logged;
+        }
+        let counted = Link::in_click_count(conn, &short_code).await;
+
+        logged.and(counted)
+    }
+
+    /// 投递点击量和访问日志作业
+    ///
+    /// 队列满时先暂存到溢出缓冲区（容量 `bg_spill_cap`），由后台定期回灌队列；
+    /// 缓冲区也满（或队列已关闭）时在当前请求内直接写 Redis，写失败才丢弃。
+    async fn enqueue_click_and_log(state: &AppState, conn: &mut Connection, job: BackgroundJob) {
+        let job = match state.bg_jobs_tx.try_send(job) {
+            Ok(()) => return,
+            Err(TrySendError::Full(job)) => {
+                let spill_cap = state.config.read().await.bg_spill_cap;
+                let mut spill = state.bg_spill.lock().unwrap_or_else(|e| e.into_inner());
+                if spill.len() < spill_cap {
+                    spill.push_back(job);
+                    state
+                        .overflow_stats
+                        .deferred
+                        .fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                job
+            }
+            Err(TrySendError::Closed(job)) => job,
+        };
 
-        Link::in_click_count(conn, &short_code).await;
+        if let BackgroundJob::PushClickAndLog {
+            short_code,
+            long_url,
+            ip,
+            user_agent,
+            referer,
+            counted,
+        } = job
+        {
+            match Self::push_click_and_log(
+                conn, short_code, long_url, ip, user_agent, referer, counted,
+            )
+            .await
+            {
+                Ok(()) => {
+                    state.overflow_stats.inline.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    state.overflow_stats.dropped.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "enqueue_click_and_log: inline write failed, dropped: {:?}",
+                        e
+                    );
+                }
+            }
+        }
     }
 
     /// 获取长链
+    ///
+    /// 有密码的短链需带有效的解锁凭证 `unlock_token`，否则返回 [`RedirectOutcome::PasswordRequired`]，
+    /// 不计点击、不扣减剩余次数。
     pub async fn get_long_url(
         ip: &str,
         user_agent: &str,
         referer: &str,
         state: &AppState,
         short_code: &str,
-    ) -> Result<String, (StatusCode, String)> {
+        unlock_token: Option<&str>,
+    ) -> Result<RedirectOutcome, (StatusCode, String)> {
         let mut conn = state.redis_pool.get().await.map_err(|e| {
             warn!("get_long_url: 获取 Redis 连接失败: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, "Redis error".into())
@@ -158,57 +669,232 @@ impl ShortlinkService {
         // redis 命中
         if let Some(long_url) = Link::get_long_url_from_redis(&mut conn, short_code).await? {
             // 异步推送点击量和访问日志
-            if let Err(e) = state.bg_jobs_tx.try_send(BackgroundJob::PushClickAndLog {
+            Self::enqueue_click_and_log(
+                state,
+                &mut conn,
+                BackgroundJob::PushClickAndLog {
+                    short_code: short_code.to_string(),
+                    long_url: long_url.clone(),
+                    ip: ip.to_string(),
+                    user_agent: user_agent.to_string(),
+                    referer: referer.to_string(),
+                    counted: false,
+                },
+            )
+            .await;
+
+            return Ok(RedirectOutcome::Redirect(long_url));
+        }
+
+        // MySQL 回溯；先读点击量同步代数，限次短链初始化剩余次数时据此判断读到的点击量是否过时
+        let sync_epoch = Link::click_sync_epoch(&mut conn).await?;
+        let target = Link::get_logn_url_from_mysql(&state.mysql_pool, short_code).await?;
+        let long_url = target.long_url;
+
+        // 限次短链次数已用完
+        if target.exhausted_at.is_some() {
+            warn!("get_long_url: link exhausted: short_code={}", short_code);
+            return Err((StatusCode::GONE, "Link has reached its click limit".into()));
+        }
+
+        let config = state.config.read().await;
+        let (redis_max_ttl, redis_min_cache_ttl) =
+            (config.redis_max_ttl, config.redis_min_cache_ttl);
+        let jwt_secret = config.jwt_secret.clone();
+        let grace_period = config.shortlink_expiry_grace_period;
+        let not_active = (
+            StatusCode::from_u16(config.link_not_active_status).unwrap_or(StatusCode::NOT_FOUND),
+            config.link_not_active_message.clone(),
+        );
+        drop(config);
+
+        // 尚未到生效时间
+        let activate_at = target.activate_at.map(|t| t.and_utc());
+        if !is_activated(activate_at) {
+            warn!(
+                "get_long_url: link not active yet: short_code={}",
+                short_code
+            );
+            return Err(not_active);
+        }
+
+        // 剩余有效期（秒），None 为永久
+        let remaining_ttl = match target.expire_at {
+            Some(expire) => {
+                let now_ts = chrono::Utc::now().timestamp();
+                let ttl = expire.and_utc().timestamp() - now_ts;
+                // 已过期
+                if ttl <= 0 {
+                    warn!("get_long_url: link expired: short_code={}", short_code);
+                    return expired_outcome(
+                        expire.and_utc(),
+                        grace_period,
+                        target.expired_landing_url,
+                    );
+                }
+                Some(ttl)
+            }
+            None => None,
+        };
+
+        // 闲置过期：距最近点击（含尚未同步的，从未点击则为创建时间）超过闲置天数
+        let inactive_ttl = match target.inactivity_days {
+            Some(days) => {
+                let pending = Link::get_pending_last_click(&mut conn, short_code).await?;
+                let last_active = target
+                    .last_click_at
+                    .map(|t| t.and_utc())
+                    .max(pending)
+                    .unwrap_or(target.created_at.and_utc());
+                let inactive_at = last_active + chrono::Duration::days(i64::from(days));
+                let ttl = (inactive_at - Utc::now()).num_seconds();
+                if ttl <= 0 {
+                    warn!("get_long_url: link inactive: short_code={}", short_code);
+                    return expired_outcome(inactive_at, grace_period, target.expired_landing_url);
+                }
+                Some(ttl)
+            }
+            None => None,
+        };
+
+        // 有密码的短链校验解锁凭证
+        if target.password_hash.is_some() {
+            let unlocked = unlock_token.is_some_and(|token| {
+                link_password::verify_unlock_token(&jwt_secret, short_code, token)
+            });
+            if !unlocked {
+                return Ok(RedirectOutcome::PasswordRequired);
+            }
+        }
+
+        match target.max_clicks {
+            // 限次短链不缓存，每次在 Redis 中原子扣减剩余次数
+            Some(max_clicks) => {
+                let remaining = Link::consume_click_budget(
+                    &state.mysql_pool,
+                    &mut conn,
+                    short_code,
+                    max_clicks,
+                    target.click_count,
+                    sync_epoch,
+                    remaining_ttl,
+                )
+                .await?;
+                match remaining {
+                    Some(0) | None => {
+                        // 最后一次跳转后立即标记为已用完，之后的访问不再依赖 Redis；
+                        // 标记失败时 Redis 中剩余次数为 0，仍会返回 410，下次访问再补标记
+                        let _ = Link::mark_exhausted(&state.mysql_pool, short_code).await;
+                        if remaining.is_none() {
+                            warn!("get_long_url: link exhausted: short_code={}", short_code);
+                            return Err((
+                                StatusCode::GONE,
+                                "Link has reached its click limit".into(),
+                            ));
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+            // 有密码的短链不缓存，否则命中缓存时会跳过密码校验
+            None if target.password_hash.is_some() => {}
+            None => {
+                // 未过期，且剩余时间大于redis缓存最小剩余有效期；永久短链按最大存储时间缓存
+                // 按闲置过期的短链缓存时长不超过剩余闲置时间，缓存过期后回源重新计算
+                let cache_window = match (remaining_ttl, inactive_ttl) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                let cache_ttl = match cache_window {
+                    Some(ttl) => (ttl > redis_min_cache_ttl).then(|| ttl.min(redis_max_ttl)),
+                    None => Some(redis_max_ttl),
+                };
+                if let Some(cache_ttl) = cache_ttl {
+                    Link::set_shortlink(&mut conn, short_code, &long_url, cache_ttl, activate_at)
+                        .await?;
+                }
+            }
+        }
+
+        // 异步推送点击量和访问日志；限次短链的点击量已在扣减剩余次数时记入
+        Self::enqueue_click_and_log(
+            state,
+            &mut conn,
+            BackgroundJob::PushClickAndLog {
                 short_code: short_code.to_string(),
                 long_url: long_url.clone(),
                 ip: ip.to_string(),
                 user_agent: user_agent.to_string(),
                 referer: referer.to_string(),
-            }) {
-                warn!(
-                    "get_long_url: failed to enqueue click/log job: short_code={} err={}",
-                    short_code, e
-                );
-            }
+                counted: target.max_clicks.is_some(),
+            },
+        )
+        .await;
 
-            return Ok(long_url);
-        }
+        Ok(RedirectOutcome::Redirect(long_url))
+    }
 
-        // MySQL 回溯
-        let (long_url, expire_opt) =
-            Link::get_logn_url_from_mysql(&state.mysql_pool, short_code).await?;
-
-        // 有设置过期时间(None为永久)
-        if let Some(expire) = expire_opt {
-            let now_ts = chrono::Utc::now().timestamp();
-            let ttl = expire.and_utc().timestamp() - now_ts;
-            // 已过期
-            if ttl <= 0 {
-                warn!("get_long_url: link expired: short_code={}", short_code);
-                return Err((StatusCode::NOT_FOUND, "Link expired".into()));
-            }
+    /// 校验短链密码，成功时返回解锁凭证
+    ///
+    /// 按 IP + 短码统计失败次数，窗口内达到 `link_unlock_fail_limit` 后返回 429，
+    /// 在窗口结束前即使密码正确也不再校验。
+    pub async fn unlock_link(
+        state: &AppState,
+        ip: &str,
+        short_code: &str,
+        password: &str,
+    ) -> Result<String, (StatusCode, String)> {
+        let (fail_limit, fail_window, unlock_ttl, jwt_secret) = {
+            let config = state.config.read().await;
+            (
+                config.link_unlock_fail_limit,
+                config.link_unlock_fail_window,
+                config.link_unlock_ttl,
+                config.jwt_secret.clone(),
+            )
+        };
 
-            // 未过期，且剩余时间大于redis缓存最小剩余有效期
-            if ttl > state.config.read().await.redis_min_cache_ttl {
-                Link::set_shortlink(&mut conn, short_code, &long_url, ttl).await?;
-            }
+        let mut conn = state.redis_pool.get().await.map_err(|e| {
+            warn!("unlock_link: 获取 Redis 连接失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Redis error".into())
+        })?;
+
+        let key = format!("rate_limit:unlock:{}:{}", ip, short_code);
+        let limited = is_limited(&key, fail_limit, &mut conn)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if limited {
+            warn!(
+                "unlock_link: 解锁失败次数过多: ip={}, short_code={}",
+                ip, short_code
+            );
+            return Err((StatusCode::TOO_MANY_REQUESTS, "Too many attempts".into()));
         }
 
-        // 异步推送点击量和访问日志
-        if let Err(e) = state.bg_jobs_tx.try_send(BackgroundJob::PushClickAndLog {
-            short_code: short_code.to_string(),
-            long_url: long_url.clone(),
-            ip: ip.to_string(),
-            user_agent: user_agent.to_string(),
-            referer: referer.to_string(),
-        }) {
+        let target = Link::get_logn_url_from_mysql(&state.mysql_pool, short_code).await?;
+        let Some(password_hash) = target.password_hash else {
+            warn!("unlock_link: 短链未设置密码: short_code={}", short_code);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Link is not password protected".into(),
+            ));
+        };
+
+        if !link_password::verify_password(password, &password_hash).await? {
             warn!(
-                "get_long_url: failed to enqueue click/log job after DB fallback: short_code={} err={}",
-                short_code, e
+                "unlock_link: 密码错误: ip={}, short_code={}",
+                ip, short_code
             );
+            if rate_limit(&key, fail_limit, fail_window, &mut conn)
+                .await
+                .is_err()
+            {
+                return Err((StatusCode::TOO_MANY_REQUESTS, "Too many attempts".into()));
+            }
+            return Err((StatusCode::UNAUTHORIZED, "Invalid password".into()));
         }
 
-        Ok(long_url)
+        link_password::issue_unlock_token(&jwt_secret, short_code, unlock_ttl)
     }
 
     /// 获取短链列表
@@ -222,6 +908,162 @@ impl ShortlinkService {
         Ok((links, count))
     }
 
+    /// 编辑短链，`expire_at` 为 `Some(None)` 时改为永久有效，`expired_landing_url` 为空串时清除落地页
+    ///
+    /// 提交后删除跳转缓存，下次访问回源 MySQL 读到新值；编辑期间并发回源的请求可能把旧值
+    /// 写回缓存，因此延迟 `cache_double_delete_delay_ms` 后再删一次。
+    /// 点击增量存放在不带过期时间的哈希中，不受有效期变更影响。
+    pub async fn update_link(
+        state: &AppState,
+        id: u64,
+        user_id: u64,
+        long_url: Option<&str>,
+        expire_at: Option<Option<DateTime<Utc>>>,
+        expired_landing_url: Option<&str>,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let long_url = long_url
+            .map(|url| Self::resolve_destination(state, url).map(|canonical| (url, canonical)))
+            .transpose()?;
+        let expired_landing_url = expired_landing_url
+            .map(|url| {
+                (!url.is_empty())
+                    .then(|| Self::resolve_destination(state, url).map(|_| url))
+                    .transpose()
+            })
+            .transpose()?;
+
+        let mut tx = state.mysql_pool.begin().await.map_err(|e| {
+            warn!("update_link: DB Begin error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Begin error: {}", e),
+            )
+        })?;
+
+        let link = Link::update_link(
+            &mut tx,
+            id,
+            user_id,
+            long_url
+                .as_ref()
+                .map(|(url, canonical)| (*url, canonical.as_str())),
+            expire_at,
+            expired_landing_url,
+        )
+        .await?;
+
+        tx.commit().await.map_err(|e| {
+            warn!("update_link: DB Commit error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Commit error: {}", e),
+            )
+        })?;
+
+        Self::invalidate_after_update(state, &link.short_code).await?;
+
+        Ok(link)
+    }
+
+    /// 重新激活宽限期内的过期短链，`expire_at` 为新的过期时间（`Some(None)` 为永久有效）
+    pub async fn reactivate_link(
+        state: &AppState,
+        id: u64,
+        user_id: u64,
+        expire_at: Option<Option<DateTime<Utc>>>,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let mut tx = state.mysql_pool.begin().await.map_err(|e| {
+            warn!("reactivate_link: DB Begin error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Begin error: {}", e),
+            )
+        })?;
+
+        let link = Link::reactivate_link(&mut tx, id, user_id, expire_at).await?;
+
+        tx.commit().await.map_err(|e| {
+            warn!("reactivate_link: DB Commit error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Commit error: {}", e),
+            )
+        })?;
+
+        Self::invalidate_after_update(state, &link.short_code).await?;
+
+        Ok(link)
+    }
+
+    /// 目标地址修改记录
+    pub async fn link_history(
+        state: &AppState,
+        id: u64,
+        user_id: u64,
+    ) -> Result<Vec<LinkRevisionView>, (StatusCode, String)> {
+        Link::find_revisions(&state.mysql_pool, id, user_id).await
+    }
+
+    /// 回滚到第 `revision` 次修改之前的目标地址
+    pub async fn rollback_link(
+        state: &AppState,
+        id: u64,
+        user_id: u64,
+        revision: u32,
+    ) -> Result<LinkView, (StatusCode, String)> {
+        let mut tx = state.mysql_pool.begin().await.map_err(|e| {
+            warn!("rollback_link: DB Begin error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Begin error: {}", e),
+            )
+        })?;
+
+        let link = Link::rollback_link(&mut tx, id, user_id, revision, |url| {
+            Self::resolve_destination(state, url)
+        })
+        .await?;
+
+        tx.commit().await.map_err(|e| {
+            warn!("rollback_link: DB Commit error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB Commit error: {}", e),
+            )
+        })?;
+
+        Self::invalidate_after_update(state, &link.short_code).await?;
+
+        Ok(link)
+    }
+
+    /// 删除跳转缓存，并延迟再删一次
+    async fn invalidate_after_update(
+        state: &AppState,
+        short_code: &str,
+    ) -> Result<(), (StatusCode, String)> {
+        let mut conn = state.redis_pool.get().await.map_err(|e| {
+            warn!("invalidate_after_update: 获取 Redis 连接失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Redis error".into())
+        })?;
+        Link::invalidate_shortlink(&mut conn, short_code).await?;
+
+        let delay = state.config.read().await.cache_double_delete_delay_ms;
+        let redis_pool = state.redis_pool.clone();
+        let short_code = short_code.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            match redis_pool.get().await {
+                Ok(mut conn) => {
+                    let _ = Link::invalidate_shortlink(&mut conn, &short_code).await;
+                }
+                Err(e) => warn!("invalidate_after_update: 获取 Redis 连接失败: {}", e),
+            }
+        });
+
+        Ok(())
+    }
+
     /// 删除短链
     pub async fn delete_links(
         state: &AppState,
@@ -281,15 +1123,3 @@ impl ShortlinkService {
             .await
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_encode_base62() {
-        assert_eq!(ShortlinkService::encode_base62(1), "1");
-        assert_eq!(ShortlinkService::encode_base62(62), "10");
-        assert_eq!(ShortlinkService::encode_base62(62 * 62), "100");
-    }
-}
@@ -0,0 +1,168 @@
+//! 目标地址规范化
+//!
+//! 创建和编辑短链时把目标地址转换为规范形式，与原始地址一起存储：跳转仍使用原始地址，
+//! 规范形式（`links.canonical_url`）用于去重（取 SHA-256 存于 `links.url_hash`）、搜索和黑名单匹配。
+//!
+//! 规范化步骤：
+//! - 协议和主机转小写，国际化域名转为 punycode，去掉默认端口，解析路径中的 `.`/`..`（由 `url` 解析完成）
+//! - 百分号编码统一：非保留字符解码，其余转义序列统一为大写十六进制
+//! - 按配置去掉指定的查询参数、对查询参数排序；空查询串和空片段去掉
+use axum::http::StatusCode;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use url::Url;
+
+use crate::config::AppConfig;
+
+pub struct UrlCanonicalizer {
+    /// 是否按参数名排序查询参数
+    sort_query: bool,
+    /// 要去掉的查询参数名（小写），以 `*` 结尾表示前缀匹配
+    strip_params: Vec<String>,
+}
+
+impl UrlCanonicalizer {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self::new(config.url_sort_query_params, &config.url_strip_query_params)
+    }
+
+    fn new(sort_query: bool, strip_params: &str) -> Self {
+        Self {
+            sort_query,
+            strip_params: strip_params
+                .split(',')
+                .map(|p| p.trim().to_ascii_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    /// 返回规范形式，无法解析时返回 400
+    pub fn canonicalize(&self, raw: &str) -> Result<String, (StatusCode, String)> {
+        let mut url = Url::parse(raw.trim()).map_err(|e| {
+            warn!("canonicalize: URL 解析失败: url={:?}, err={}", raw, e);
+            (StatusCode::BAD_REQUEST, "Invalid URL".into())
+        })?;
+
+        let path = normalize_percent(url.path());
+        url.set_path(&path);
+
+        let query = url.query().map(|q| self.canonical_query(q));
+        url.set_query(query.as_deref().filter(|q| !q.is_empty()));
+
+        let fragment = url.fragment().map(normalize_percent);
+        url.set_fragment(fragment.as_deref().filter(|f| !f.is_empty()));
+
+        Ok(url.to_string())
+    }
+
+    fn canonical_query(&self, query: &str) -> String {
+        let mut pairs: Vec<(String, String)> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let pair = normalize_percent(pair);
+                let key = pair.split('=').next().unwrap_or_default().to_string();
+                (key, pair)
+            })
+            .filter(|(key, _)| !self.is_stripped(key))
+            .collect();
+
+        // 稳定排序，同名参数保持原有顺序
+        if self.sort_query {
+            pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        pairs
+            .into_iter()
+            .map(|(_, pair)| pair)
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn is_stripped(&self, key: &str) -> bool {
+        let key = key.to_ascii_lowercase();
+        self.strip_params.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == *p,
+        })
+    }
+}
+
+/// 统一百分号编码：非保留字符（字母、数字、`-._~`）解码，其余转义序列转为大写
+fn normalize_percent(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) if b.is_ascii_alphanumeric() || b"-._~".contains(&b) => {
+                out.push(b as char);
+                i += 3;
+            }
+            Some(b) => {
+                out.push_str(&format!("%{:02X}", b));
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i] as char);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// 规范形式的哈希（32 字节）
+pub fn url_hash(canonical_url: &str) -> Vec<u8> {
+    Sha256::digest(canonical_url.as_bytes()).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let c = UrlCanonicalizer::new(false, "");
+        assert_eq!(
+            c.canonicalize("HTTP://Example.com:80/a/../b?utm=1")
+                .unwrap(),
+            "http://example.com/b?utm=1"
+        );
+        assert_eq!(
+            c.canonicalize(" https://Bücher.example:443 ").unwrap(),
+            "https://xn--bcher-kva.example/"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/%7euser/%2fx?q=%e4%b8%ad#")
+                .unwrap(),
+            "https://example.com/~user/%2Fx?q=%E4%B8%AD"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/a/%2E%2E/b?").unwrap(),
+            "https://example.com/b"
+        );
+        assert!(c.canonicalize("not a url").is_err());
+    }
+
+    #[test]
+    fn test_canonical_query() {
+        let c = UrlCanonicalizer::new(true, " UTM_*, fbclid ");
+        assert_eq!(
+            c.canonicalize("https://example.com/?b=2&utm_source=x&a=1&FBCLID=y&&a=0")
+                .unwrap(),
+            "https://example.com/?a=1&a=0&b=2"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/p?utm_medium=email")
+                .unwrap(),
+            "https://example.com/p"
+        );
+    }
+}
@@ -1,8 +1,16 @@
 use crate::config::AppConfig;
 use crate::services::background_jobs::BackgroundJob;
+use crate::services::code_policy::CodePolicy;
+use crate::services::destination_policy::DestinationPolicy;
+use crate::services::short_code::ShortCodeGenerator;
+use crate::services::url_normalize::UrlCanonicalizer;
 use dashmap::DashSet;
 use deadpool_redis::Pool;
 use sqlx::MySqlPool;
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, atomic::AtomicU64},
+};
 use tokio::sync::{RwLock, mpsc::Sender};
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
@@ -12,10 +20,34 @@ pub enum ScheduledJobKind {
     DeleteExpired,
 }
 
+/// 后台作业队列溢出统计
+#[derive(Debug, Default)]
+pub struct OverflowStats {
+    /// 队列满后暂存到溢出缓冲区的事件数
+    pub deferred: AtomicU64,
+    /// 队列和溢出缓冲区都满时在请求内直接写入 Redis 的事件数
+    pub inline: AtomicU64,
+    /// 最终丢弃的事件数
+    pub dropped: AtomicU64,
+}
+
 pub struct AppState {
     pub mysql_pool: MySqlPool,
     pub redis_pool: Pool,
     pub config: RwLock<AppConfig>,
     pub bg_jobs_tx: Sender<BackgroundJob>,
     pub pending_set: DashSet<ScheduledJobKind>,
+    /// 队列满时暂存的后台作业，由后台定期回灌队列
+    pub bg_spill: Mutex<VecDeque<BackgroundJob>>,
+    pub overflow_stats: OverflowStats,
+    /// 当前副本的唯一标识，用于跨副本的定时任务租约
+    pub instance_id: String,
+    /// 自动短码生成器，启动时按配置创建
+    pub code_generator: Box<dyn ShortCodeGenerator>,
+    /// 自定义短码校验规则，启动时按配置创建
+    pub code_policy: CodePolicy,
+    /// 目标地址规范化规则，启动时按配置创建
+    pub url_canonicalizer: UrlCanonicalizer,
+    /// 目标地址安全策略，域名黑名单由后台任务定期重新加载
+    pub destination_policy: DestinationPolicy,
 }
@@ -57,6 +57,8 @@ pub struct AppConfig {
     pub mysql_query_timeout_ms: u64,
     /// InnoDB 表中等待锁的最大时间（秒）
     pub mysql_lock_wait_timeout_s: u64,
+    /// 启动时是否执行数据库迁移
+    pub run_migrations: bool,
 
     /// Redis 连接池最大连接数
     pub redis_pool_size: usize,
@@ -109,6 +111,22 @@ mod tests {
             env::set_var("IP_REGISTER_TTL", "86400");
             env::set_var("USER_RATE_LIMIT", "200");
             env::set_var("USER_RATE_LIMIT_WINDOW", "60");
+            env::set_var("SHORTLINK_MIN_TTL", "60");
+            env::set_var("SHORTLINK_MAX_TTL", "3600");
+            env::set_var("REDIS_MAX_TTL", "86400");
+            env::set_var("REDIS_MIN_CACHE_TTL", "60");
+            env::set_var("MAX_STATS_DAYS", "30");
+            env::set_var("USER_TOKEN_LIMIT", "5");
+            env::set_var("GLOBAL_TIMEOUT_MS", "5000");
+            env::set_var("MYSQL_MAX_CONNECTIONS", "10");
+            env::set_var("MYSQL_ACQUIRE_TIMEOUT_MS", "3000");
+            env::set_var("MYSQL_QUERY_TIMEOUT_MS", "3000");
+            env::set_var("MYSQL_LOCK_WAIT_TIMEOUT_S", "5");
+            env::set_var("RUN_MIGRATIONS", "false");
+            env::set_var("REDIS_POOL_SIZE", "16");
+            env::set_var("REDIS_TIMEOUT_WAIT_MS", "1000");
+            env::set_var("REDIS_TIMEOUT_CREATE_MS", "1000");
+            env::set_var("REDIS_TIMEOUT_RECYCLE_MS", "1000");
         }
 
         let cfg = AppConfig::from_env().expect("load config");
@@ -122,5 +140,6 @@ mod tests {
         assert_eq!(cfg.user_rate_limit_window, 60);
         assert_eq!(cfg.ip_register_limit, 5);
         assert_eq!(cfg.user_rate_limit, 200);
+        assert!(!cfg.run_migrations);
     }
 }
@@ -5,6 +5,7 @@ use axum::{
 use std::{net::SocketAddr, sync::Arc};
 use tokio::{net::TcpListener, sync::RwLock};
 use tower_http::trace::TraceLayer;
+use tracing::info;
 use tracing_subscriber::fmt::time::LocalTime;
 
 use common::db;
@@ -32,6 +33,14 @@ async fn main() {
     .await
     .unwrap();
 
+    // 按需执行数据库迁移
+    if cfg.run_migrations {
+        db::run_migrations(&mysql_pool)
+            .await
+            .expect("run database migrations");
+        info!("database migrations applied");
+    }
+
     let redis_pool = db::new_redis_pool(
         &cfg.redis_url,
         cfg.redis_pool_size,
@@ -1,9 +1,7 @@
 use crate::{models::session::create_session, models::user::User, state::AppState};
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::http::StatusCode;
-use common::models::Claims;
+use common::{models::Claims, password};
 use jsonwebtoken::{EncodingKey, Header, encode};
-use password_hash::{PasswordHasher, SaltString, rand_core::OsRng};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 use uuid::Uuid;
@@ -46,21 +44,10 @@ impl UserService {
             return Err((StatusCode::BAD_REQUEST, "Email already registered".into()));
         }
         // 生成随机盐加密密码
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let hashed_pwd = argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|e| {
-                warn!(
-                    "register: password encryption failed: email={}, err={}",
-                    email, e
-                );
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Password encryption failed: {}", e),
-                )
-            })?
-            .to_string();
+        let hashed_pwd = password::hash_password(password).await.map_err(|e| {
+            warn!("register: password encryption failed: email={}", email);
+            (StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?;
 
         // 记录注册次数
         User::record_register(&mut conn, &ip_register_key, ip_register_ttl).await?;
@@ -112,17 +99,15 @@ impl UserService {
         .await?;
 
         // 验证密码 (argon2)
-        let parsed_hash = PasswordHash::new(&user.password).map_err(|_| {
-            warn!("login: password hash parse failed: email={}", email);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Password hash parse failed".into(),
-            )
-        })?;
+        let verified = password::verify_password(password, &user.password)
+            .await
+            .map_err(|e| {
+                warn!("login: password hash parse failed: email={}", email);
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            })?;
 
-        let argon2 = Argon2::default();
         // 验证密码失败时记录失败并返回
-        if let Err(_) = argon2.verify_password(password.as_bytes(), &parsed_hash) {
+        if !verified {
             warn!("login: invalid password: email={}", email);
             let user_login_fail_ttl = config.user_login_fail_ttl;
             let ip_user_login_fail_ttl = config.ip_user_login_fail_ttl;